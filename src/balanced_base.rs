@@ -0,0 +1,485 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+    iter::Sum,
+    marker::PhantomData,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+// Digit alphabet for base `B`. `SYMBOLS[i]` is the symbol of the digit with
+// value `LOWEST + i`, so `LOWEST == 0` describes a standard base and a
+// negative `LOWEST` a balanced (or otherwise signed) one.
+pub trait Alphabet<const B: u32> {
+    const LOWEST: i32;
+    const SYMBOLS: &'static [char];
+    // Prefix used for negative numbers; required when no digit is negative.
+    const MINUS: Option<char> = None;
+}
+
+const fn validate(base: u32, lowest: i32, symbols: &[char], minus: Option<char>) {
+    assert!(base >= 2, "base must be at least 2");
+    assert!(symbols.len() == base as usize, "one symbol per digit");
+    assert!(lowest <= 0, "digit range must contain zero");
+    assert!(lowest + base as i32 > 1, "digit range must contain one");
+    assert!(lowest < 0 || minus.is_some(), "standard bases need a minus");
+
+    let mut i = 0;
+    while i < symbols.len() {
+        let mut j = i + 1;
+        while j < symbols.len() {
+            assert!(symbols[i] != symbols[j], "digit symbols must be unique");
+            j += 1;
+        }
+        if let Some(minus) = minus {
+            assert!(symbols[i] != minus, "minus must not be a digit");
+        }
+        i += 1;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NumeralError {
+    Empty,
+    InvalidDigit { position: usize, found: char },
+    Overflow,
+}
+
+impl Display for NumeralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumeralError::Empty => write!(f, "empty number"),
+            NumeralError::InvalidDigit { position, found } => {
+                write!(f, "invalid digit {found:?} at position {position}")
+            }
+            NumeralError::Overflow => write!(f, "number does not fit into an i64"),
+        }
+    }
+}
+
+impl std::error::Error for NumeralError {}
+
+// Arbitrary precision integer in base `B` with the digits of alphabet `A`.
+//
+// The value is `sum(digits[i] * B^i) + tail * B^digits.len()`. `tail` is zero
+// unless the alphabet has no negative digits, in which case negative numbers
+// keep a tail of -1 (the radix complement), so arithmetic stays digit-wise.
+pub struct BalancedBase<const B: u32, A: Alphabet<B>> {
+    digits: Vec<i32>,
+    tail: i32,
+    alphabet: PhantomData<A>,
+}
+
+impl<const B: u32, A: Alphabet<B>> BalancedBase<B, A> {
+    const VALID: () = validate(B, A::LOWEST, A::SYMBOLS, A::MINUS);
+
+    pub fn zero() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+
+        Self {
+            digits: vec![],
+            tail: 0,
+            alphabet: PhantomData,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty() && self.tail == 0
+    }
+
+    pub fn signum(&self) -> i32 {
+        match self.tail {
+            0 => self.digits.last().map_or(0, |digit| digit.signum()),
+            tail => tail.signum(),
+        }
+    }
+
+    // Splits `value` into a digit of the alphabet and the carry to the next
+    // position.
+    fn balance(value: i128) -> (i32, i128) {
+        let carry = (value - A::LOWEST as i128).div_euclid(B as i128);
+        ((value - carry * B as i128) as i32, carry)
+    }
+
+    // Builds a number from arbitrary (possibly out of range) coefficients of
+    // increasing powers of `B`.
+    fn normalise(coefficients: impl Iterator<Item = i128>) -> Self {
+        let mut digits = vec![];
+        let mut carry = 0;
+        for coefficient in coefficients {
+            let (digit, next_carry) = Self::balance(coefficient + carry);
+            digits.push(digit);
+            carry = next_carry;
+        }
+        loop {
+            let (digit, next_carry) = Self::balance(carry);
+            if next_carry == carry {
+                break;
+            }
+            digits.push(digit);
+            carry = next_carry;
+        }
+
+        let tail = carry as i32;
+        let fill = tail * (1 - B as i32);
+        while digits.last() == Some(&fill) {
+            digits.pop();
+        }
+
+        Self {
+            digits,
+            tail,
+            ..Self::zero()
+        }
+    }
+
+    fn coefficients(&self) -> impl Iterator<Item = i128> + '_ {
+        self.digits
+            .iter()
+            .chain([&self.tail])
+            .map(|&digit| digit as i128)
+    }
+
+    fn scale(&self, factor: i32) -> Self {
+        Self::normalise(self.coefficients().map(|digit| digit * factor as i128))
+    }
+
+    fn shift(mut self, places: usize) -> Self {
+        if !self.is_zero() {
+            self.digits.splice(0..0, std::iter::repeat_n(0, places));
+        }
+        self
+    }
+
+    fn write_digits(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = |digit: &i32| A::SYMBOLS[(digit - A::LOWEST) as usize];
+        if self.digits.is_empty() {
+            return write!(f, "{}", symbol(&0));
+        }
+        self.digits
+            .iter()
+            .rev()
+            .try_for_each(|digit| write!(f, "{}", symbol(digit)))
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> FromStr for BalancedBase<B, A> {
+    type Err = NumeralError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, offset, magnitude) = match A::MINUS.and_then(|m| s.strip_prefix(m)) {
+            Some(rest) => (true, s.len() - rest.len(), rest),
+            None => (false, 0, s),
+        };
+        if magnitude.is_empty() {
+            return Err(NumeralError::Empty);
+        }
+
+        let digits = magnitude
+            .chars()
+            .enumerate()
+            .map(|(position, found)| {
+                A::SYMBOLS
+                    .iter()
+                    .position(|symbol| *symbol == found)
+                    .map(|index| index as i128 + A::LOWEST as i128)
+                    .ok_or(NumeralError::InvalidDigit {
+                        position: position + offset,
+                        found,
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let number = Self::normalise(digits.into_iter().rev());
+        Ok(if negative { -number } else { number })
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> Display for BalancedBase<B, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match A::MINUS {
+            Some(minus) if self.tail != 0 => {
+                write!(f, "{minus}")?;
+                (-self.clone()).write_digits(f)
+            }
+            _ => self.write_digits(f),
+        }
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> fmt::Debug for BalancedBase<B, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}_{B}")
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> Clone for BalancedBase<B, A> {
+    fn clone(&self) -> Self {
+        Self {
+            digits: self.digits.clone(),
+            tail: self.tail,
+            alphabet: PhantomData,
+        }
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> Default for BalancedBase<B, A> {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> PartialEq for BalancedBase<B, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.digits == other.digits && self.tail == other.tail
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> Eq for BalancedBase<B, A> {}
+
+impl<const B: u32, A: Alphabet<B>> Hash for BalancedBase<B, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.digits.hash(state);
+        self.tail.hash(state);
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> From<i64> for BalancedBase<B, A> {
+    fn from(value: i64) -> Self {
+        Self::normalise([value as i128].into_iter())
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> TryFrom<&BalancedBase<B, A>> for i64 {
+    type Error = NumeralError;
+
+    fn try_from(value: &BalancedBase<B, A>) -> Result<Self, Self::Error> {
+        value
+            .digits
+            .iter()
+            .rev()
+            .try_fold(value.tail as i64, |acc, digit| {
+                acc.checked_mul(B as i64)
+                    .and_then(|acc| acc.checked_add(*digit as i64))
+                    .ok_or(NumeralError::Overflow)
+            })
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> Neg for BalancedBase<B, A> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self.scale(-1)
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> Add for BalancedBase<B, A> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let length = self.digits.len().max(rhs.digits.len()) + 1;
+        let coefficients = |number: &Self| {
+            number
+                .coefficients()
+                .chain(std::iter::repeat(0))
+                .take(length)
+                .collect::<Vec<_>>()
+        };
+        let (left, right) = (coefficients(&self), coefficients(&rhs));
+
+        Self::normalise(left.iter().zip(right).map(|(l, r)| l + r))
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> Sub for BalancedBase<B, A> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> Mul for BalancedBase<B, A> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        rhs.digits
+            .iter()
+            .chain([&rhs.tail])
+            .enumerate()
+            .map(|(i, digit)| self.scale(*digit).shift(i))
+            .sum()
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> Sum for BalancedBase<B, A> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, x| acc + x)
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> Ord for BalancedBase<B, A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.clone() - other.clone()).signum().cmp(&0)
+    }
+}
+
+impl<const B: u32, A: Alphabet<B>> PartialOrd for BalancedBase<B, A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct SnafuDigits;
+
+impl Alphabet<5> for SnafuDigits {
+    const LOWEST: i32 = -2;
+    const SYMBOLS: &'static [char] = &['=', '-', '0', '1', '2'];
+}
+
+pub type Snafu = BalancedBase<5, SnafuDigits>;
+
+pub struct TernaryDigits;
+
+impl Alphabet<3> for TernaryDigits {
+    const LOWEST: i32 = -1;
+    const SYMBOLS: &'static [char] = &['-', '0', '+'];
+}
+
+pub type BalancedTernary = BalancedBase<3, TernaryDigits>;
+
+pub struct SeptenaryDigits;
+
+impl Alphabet<7> for SeptenaryDigits {
+    const LOWEST: i32 = -3;
+    const SYMBOLS: &'static [char] = &['c', 'b', 'a', '0', '1', '2', '3'];
+}
+
+pub type BalancedSeptenary = BalancedBase<7, SeptenaryDigits>;
+
+pub struct DecimalDigits;
+
+impl Alphabet<10> for DecimalDigits {
+    const LOWEST: i32 = 0;
+    const SYMBOLS: &'static [char] = &['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+    const MINUS: Option<char> = Some('-');
+}
+
+pub type Decimal = BalancedBase<10, DecimalDigits>;
+
+pub struct BinaryDigits;
+
+impl Alphabet<2> for BinaryDigits {
+    const LOWEST: i32 = 0;
+    const SYMBOLS: &'static [char] = &['0', '1'];
+    const MINUS: Option<char> = Some('-');
+}
+
+pub type Binary = BalancedBase<2, BinaryDigits>;
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(0, "0")]
+    #[case(1, "+")]
+    #[case(-1, "-")]
+    #[case(2, "+-")]
+    #[case(8, "+0-")]
+    #[case(-8, "-0+")]
+    fn test_balanced_ternary(#[case] value: i64, #[case] expected: &str) {
+        assert_eq!(BalancedTernary::from(value).to_string(), expected);
+    }
+
+    #[rstest]
+    #[case(4, "1c")]
+    #[case(-11, "b3")]
+    #[case(-10, "ac")]
+    #[case(45, "1a3")]
+    fn test_balanced_septenary(#[case] value: i64, #[case] expected: &str) {
+        assert_eq!(BalancedSeptenary::from(value).to_string(), expected);
+    }
+
+    #[rstest]
+    #[case("0", 0)]
+    #[case("-0", 0)]
+    #[case("007", 7)]
+    #[case("-1203", -1203)]
+    fn test_decimal(#[case] input: Decimal, #[case] expected: i64) {
+        assert_eq!(i64::try_from(&input), Ok(expected));
+        assert_eq!(input.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!("-".parse::<Decimal>(), Err(NumeralError::Empty));
+        assert_eq!(
+            "-12a".parse::<Decimal>(),
+            Err(NumeralError::InvalidDigit {
+                position: 3,
+                found: 'a'
+            })
+        );
+        assert_eq!(
+            "+0=".parse::<BalancedTernary>(),
+            Err(NumeralError::InvalidDigit {
+                position: 2,
+                found: '='
+            })
+        );
+    }
+
+    fn check_round_trip<const B: u32, A: Alphabet<B>>(value: i64) -> Result<(), TestCaseError> {
+        let number = BalancedBase::<B, A>::from(value);
+        let parsed = number.to_string().parse::<BalancedBase<B, A>>().unwrap();
+        prop_assert_eq!(&parsed, &number);
+        prop_assert_eq!(i64::try_from(&parsed), Ok(value));
+        Ok(())
+    }
+
+    fn check_arithmetic<const B: u32, A: Alphabet<B>>(
+        left: i64,
+        right: i64,
+    ) -> Result<(), TestCaseError> {
+        let l = BalancedBase::<B, A>::from(left);
+        let r = BalancedBase::<B, A>::from(right);
+        prop_assert_eq!(l.clone() + r.clone(), (left + right).into());
+        prop_assert_eq!(l.clone() - r.clone(), (left - right).into());
+        prop_assert_eq!(l.clone() * r.clone(), (left * right).into());
+        prop_assert_eq!(-l.clone(), (-left).into());
+        prop_assert_eq!(l.cmp(&r), left.cmp(&right));
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(value in any::<i64>()) {
+            check_round_trip::<5, SnafuDigits>(value)?;
+            check_round_trip::<3, TernaryDigits>(value)?;
+            check_round_trip::<7, SeptenaryDigits>(value)?;
+            check_round_trip::<10, DecimalDigits>(value)?;
+            check_round_trip::<2, BinaryDigits>(value)?;
+        }
+
+        #[test]
+        fn decimal_matches_std(value in any::<i64>()) {
+            prop_assert_eq!(Decimal::from(value).to_string(), value.to_string());
+        }
+
+        #[test]
+        fn arithmetic(left in -1_000_000_000i64..1_000_000_000, right in -1_000_000_000i64..1_000_000_000) {
+            check_arithmetic::<5, SnafuDigits>(left, right)?;
+            check_arithmetic::<3, TernaryDigits>(left, right)?;
+            check_arithmetic::<7, SeptenaryDigits>(left, right)?;
+            check_arithmetic::<10, DecimalDigits>(left, right)?;
+            check_arithmetic::<2, BinaryDigits>(left, right)?;
+        }
+    }
+}
@@ -1,30 +1,51 @@
+use std::{cmp::Reverse, collections::BinaryHeap, io::BufRead, num::ParseIntError};
+
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::Itertools;
+use itertools::{process_results, Itertools};
+
+use crate::streaming::{groups, StreamError};
+
+type Parsed = Vec<u128>;
+
+// Calories carried by each elf, read one group at a time.
+pub fn calorie_totals<R: BufRead>(
+    reader: R,
+) -> impl Iterator<Item = Result<u128, StreamError<ParseIntError>>> {
+    groups(reader).map_ok(|group: Vec<u128>| group.iter().sum())
+}
+
+// Sum of the `n` largest totals, keeping only `n` of them in memory.
+pub fn top_total(totals: impl IntoIterator<Item = u128>, n: usize) -> u128 {
+    let mut top = BinaryHeap::with_capacity(n + 1);
+    for total in totals {
+        top.push(Reverse(total));
+        if top.len() > n {
+            top.pop();
+        }
+    }
+    top.into_iter().map(|Reverse(total)| total).sum()
+}
 
-type Parsed = Vec<Vec<u128>>;
+pub fn top_total_streaming<R: BufRead>(
+    reader: R,
+    n: usize,
+) -> Result<u128, StreamError<ParseIntError>> {
+    process_results(calorie_totals(reader), |totals| top_total(totals, n))
+}
 
 #[aoc_generator(day1)]
-fn parse_input(input: &str) -> Parsed {
-    input
-        .split("\n\n")
-        .map(|s| s.lines().map(|l| l.parse::<u128>().unwrap()).collect())
-        .collect()
+fn parse_input(input: &str) -> Result<Parsed, StreamError<ParseIntError>> {
+    calorie_totals(input.as_bytes()).collect()
 }
 
 #[aoc(day1, part1)]
 fn part1(input: &Parsed) -> u128 {
-    input.iter().map(|group| group.iter().sum()).max().unwrap()
+    top_total(input.iter().copied(), 1)
 }
 
 #[aoc(day1, part2)]
 fn part2(input: &Parsed) -> u128 {
-    input
-        .iter()
-        .map(|group| group.iter().sum::<u128>())
-        .sorted()
-        .rev()
-        .take(3)
-        .sum()
+    top_total(input.iter().copied(), 3)
 }
 
 #[cfg(test)]
@@ -50,11 +71,20 @@ mod tests {
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&parse_input(input())), 24000);
+        assert_eq!(part1(&parse_input(input()).unwrap()), 24000);
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&parse_input(input())), 45000);
+        assert_eq!(part2(&parse_input(input()).unwrap()), 45000);
+    }
+
+    #[test]
+    fn streaming() {
+        assert_eq!(top_total_streaming(input().as_bytes(), 2).unwrap(), 35000);
+        assert!(matches!(
+            top_total_streaming("1\n\nx".as_bytes(), 1),
+            Err(StreamError::Parse { line: 3, .. })
+        ));
     }
 }
@@ -1,125 +1,247 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::{
-    cmp::{Ordering, PartialOrd},
-    str::FromStr,
-};
+use itertools::Itertools;
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
 
-type Parsed = Vec<(Shape, String)>;
+type Parsed = Vec<Round>;
 
-#[derive(PartialEq, Clone, Copy)]
-enum Shape {
-    Rock,
-    Paper,
-    Scissors,
+// Index into a game's cyclic order of shapes. A shape beats the
+// `(n - 1) / 2` shapes preceding it in that order. Shapes come from a
+// `Game`, through `shapes` or `parse_shape`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Shape(usize);
+
+impl Shape {
+    pub const ROCK: Shape = Shape(0);
+    pub const PAPER: Shape = Shape(1);
+    pub const SCISSORS: Shape = Shape(2);
+
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Outcome {
+    Loss,
+    Draw,
+    Win,
+}
+
+impl Outcome {
+    pub fn points(&self) -> usize {
+        match self {
+            Outcome::Loss => 0,
+            Outcome::Draw => 3,
+            Outcome::Win => 6,
+        }
+    }
+}
+
+// Second column of the strategy guide.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Column {
+    X,
+    Y,
+    Z,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Round {
+    pub opponent: Shape,
+    pub column: Column,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    InvalidLine { line: usize },
+    InvalidShape { line: usize, found: String },
+    InvalidColumn { line: usize, found: String },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidLine { line } => write!(f, "line {line}: expected two columns"),
+            Error::InvalidShape { line, found } => {
+                write!(f, "line {line}: invalid opponent shape '{found}'")
+            }
+            Error::InvalidColumn { line, found } => {
+                write!(f, "line {line}: invalid strategy column '{found}'")
+            }
+        }
+    }
 }
 
-#[derive(Debug)]
-struct Error;
+impl std::error::Error for Error {}
 
-impl FromStr for Shape {
-    type Err = Error;
+impl FromStr for Column {
+    type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "A" | "X" => Ok(Shape::Rock),
-            "B" | "Y" => Ok(Shape::Paper),
-            "C" | "Z" => Ok(Shape::Scissors),
-            _ => Err(Error),
+            "X" => Ok(Column::X),
+            "Y" => Ok(Column::Y),
+            "Z" => Ok(Column::Z),
+            _ => Err(()),
         }
     }
 }
 
-impl PartialOrd for Shape {
-    fn partial_cmp(&self, other: &Shape) -> Option<Ordering> {
-        match self {
-            Shape::Rock => match other {
-                Shape::Rock => Some(Ordering::Equal),
-                Shape::Paper => Some(Ordering::Less),
-                Shape::Scissors => Some(Ordering::Greater),
-            },
-            Shape::Paper => match other {
-                Shape::Rock => Some(Ordering::Greater),
-                Shape::Paper => Some(Ordering::Equal),
-                Shape::Scissors => Some(Ordering::Less),
-            },
-            Shape::Scissors => match other {
-                Shape::Rock => Some(Ordering::Less),
-                Shape::Paper => Some(Ordering::Greater),
-                Shape::Scissors => Some(Ordering::Equal),
-            },
+// How the second column of the guide is read.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Interpretation {
+    // the shape to play for X, Y and Z
+    Shapes([Shape; 3]),
+    // the outcome to aim for with X, Y and Z
+    Outcomes([Outcome; 3]),
+}
+
+impl Interpretation {
+    pub const PART1: Interpretation =
+        Interpretation::Shapes([Shape::ROCK, Shape::PAPER, Shape::SCISSORS]);
+    pub const PART2: Interpretation =
+        Interpretation::Outcomes([Outcome::Loss, Outcome::Draw, Outcome::Win]);
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Goal {
+    Maximise,
+    Minimise,
+}
+
+// Cyclic dominance game with an odd number of shapes, so every pair of
+// distinct shapes has a winner. At least three shapes are needed for every
+// outcome to be reachable. Shape `i` is worth `i + 1` points.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Game {
+    shapes: usize,
+}
+
+impl Game {
+    // rock, paper, scissors
+    pub const RPS: Game = Game { shapes: 3 };
+    // rock, spock, paper, lizard, scissors
+    pub const RPSLS: Game = Game { shapes: 5 };
+
+    pub fn new(shapes: usize) -> Option<Game> {
+        (shapes >= 3 && shapes % 2 == 1).then_some(Game { shapes })
+    }
+
+    pub fn shapes(&self) -> impl Iterator<Item = Shape> {
+        (0..self.shapes).map(Shape)
+    }
+
+    pub fn compare(&self, shape: Shape, other: Shape) -> Ordering {
+        match (shape.0 % self.shapes + self.shapes - other.0 % self.shapes) % self.shapes {
+            0 => Ordering::Equal,
+            d if d <= self.shapes / 2 => Ordering::Greater,
+            _ => Ordering::Less,
         }
     }
-}
 
-impl Shape {
-    fn points(&self) -> usize {
-        match self {
-            Shape::Rock => 1,
-            Shape::Paper => 2,
-            Shape::Scissors => 3,
+    pub fn outcome(&self, shape: Shape, opponent: Shape) -> Outcome {
+        match self.compare(shape, opponent) {
+            Ordering::Less => Outcome::Loss,
+            Ordering::Equal => Outcome::Draw,
+            Ordering::Greater => Outcome::Win,
         }
     }
 
-    fn winning_shape(&self) -> Shape {
-        match self {
-            Shape::Rock => Shape::Paper,
-            Shape::Paper => Shape::Scissors,
-            Shape::Scissors => Shape::Rock,
+    pub fn score(&self, shape: Shape, opponent: Shape) -> usize {
+        shape.0 % self.shapes + 1 + self.outcome(shape, opponent).points()
+    }
+
+    // With more than three shapes several may reach the outcome; the one
+    // worth the most points is chosen.
+    pub fn shape_for(&self, opponent: Shape, outcome: Outcome) -> Shape {
+        self.shapes()
+            .filter(|shape| self.outcome(*shape, opponent) == outcome)
+            .max_by_key(|shape| shape.0)
+            .unwrap()
+    }
+
+    pub fn parse_shape(&self, s: &str) -> Option<Shape> {
+        match s.as_bytes() {
+            [c @ b'A'..=b'Z'] if ((c - b'A') as usize) < self.shapes => {
+                Some(Shape((c - b'A') as usize))
+            }
+            _ => None,
         }
     }
 
-    fn losing_shape(&self) -> Shape {
-        match self {
-            Shape::Rock => Shape::Scissors,
-            Shape::Paper => Shape::Rock,
-            Shape::Scissors => Shape::Paper,
+    pub fn parse_guide(&self, input: &str) -> Result<Vec<Round>, Error> {
+        input
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                let line_number = i + 1;
+                let (opponent, column) = line
+                    .split_once(' ')
+                    .ok_or(Error::InvalidLine { line: line_number })?;
+                Ok(Round {
+                    opponent: self.parse_shape(opponent).ok_or(Error::InvalidShape {
+                        line: line_number,
+                        found: opponent.to_string(),
+                    })?,
+                    column: column.parse().map_err(|_| Error::InvalidColumn {
+                        line: line_number,
+                        found: column.to_string(),
+                    })?,
+                })
+            })
+            .collect()
+    }
+
+    pub fn play(&self, round: &Round, interpretation: Interpretation) -> usize {
+        let column = round.column as usize;
+        let shape = match interpretation {
+            Interpretation::Shapes(shapes) => shapes[column],
+            Interpretation::Outcomes(outcomes) => self.shape_for(round.opponent, outcomes[column]),
+        };
+        self.score(shape, round.opponent)
+    }
+
+    pub fn total_score(&self, guide: &[Round], interpretation: Interpretation) -> usize {
+        guide
+            .iter()
+            .map(|round| self.play(round, interpretation))
+            .sum()
+    }
+
+    // Tries every assignment of distinct shapes to the three columns.
+    pub fn best_mapping(&self, guide: &[Round], goal: Goal) -> Option<([Shape; 3], usize)> {
+        let rounds = guide.iter().counts();
+        let candidates = self.shapes().permutations(3).map(|shapes| {
+            let shapes = [shapes[0], shapes[1], shapes[2]];
+            let score = rounds
+                .iter()
+                .map(|(round, count)| {
+                    count * self.score(shapes[round.column as usize], round.opponent)
+                })
+                .sum::<usize>();
+            (shapes, score)
+        });
+
+        match goal {
+            Goal::Maximise => candidates.max_by_key(|(_, score)| *score),
+            Goal::Minimise => candidates.min_by_key(|(_, score)| *score),
         }
     }
 }
 
 #[aoc_generator(day2)]
-fn parse_input(input: &str) -> Parsed {
-    input
-        .lines()
-        .map(|line| {
-            let parts = line.split(" ").collect::<Vec<&str>>();
-            (parts[0].parse().unwrap(), parts[1].to_string())
-        })
-        .collect()
-}
-
-fn round_points(ordering: Ordering) -> usize {
-    match ordering {
-        Ordering::Less => 0,
-        Ordering::Greater => 6,
-        _ => 3,
-    }
+fn parse_input(input: &str) -> Result<Parsed, Error> {
+    Game::RPS.parse_guide(input)
 }
 
 #[aoc(day2, part1)]
 fn part1(input: &Parsed) -> usize {
-    input
-        .iter()
-        .map(|row| (row.0, row.1.parse::<Shape>().unwrap()))
-        .map(|round| round.1.points() + round_points(round.1.partial_cmp(&round.0).unwrap()))
-        .sum()
+    Game::RPS.total_score(input, Interpretation::PART1)
 }
 
 #[aoc(day2, part2)]
 fn part2(input: &Parsed) -> usize {
-    input
-        .iter()
-        .map(|round| points_for_strategy(round.0, &round.1))
-        .sum()
-}
-
-fn points_for_strategy(enemy_shape: Shape, str: &str) -> usize {
-    match str {
-        "X" => enemy_shape.losing_shape().points(),
-        "Y" => 3 + enemy_shape.points(),
-        "Z" => 6 + enemy_shape.winning_shape().points(),
-        _ => 0,
-    }
+    Game::RPS.total_score(input, Interpretation::PART2)
 }
 
 #[cfg(test)]
@@ -134,11 +256,85 @@ C Z"
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&parse_input(input())), 15);
+        assert_eq!(part1(&parse_input(input()).unwrap()), 15);
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&parse_input(input())), 12);
+        assert_eq!(part2(&parse_input(input()).unwrap()), 12);
+    }
+
+    #[test]
+    fn rps_rules() {
+        let game = Game::RPS;
+        assert_eq!(game.outcome(Shape::PAPER, Shape::ROCK), Outcome::Win);
+        assert_eq!(game.outcome(Shape::ROCK, Shape::SCISSORS), Outcome::Win);
+        assert_eq!(game.outcome(Shape::SCISSORS, Shape::ROCK), Outcome::Loss);
+        assert_eq!(game.outcome(Shape::PAPER, Shape::PAPER), Outcome::Draw);
+        assert_eq!(game.shape_for(Shape::ROCK, Outcome::Loss), Shape::SCISSORS);
+    }
+
+    #[test]
+    fn rpsls_rules() {
+        let game = Game::RPSLS;
+        let (rock, spock, paper, lizard, scissors) = game.shapes().collect_tuple().unwrap();
+        for (winner, loser) in [
+            (scissors, paper),
+            (paper, rock),
+            (rock, lizard),
+            (lizard, spock),
+            (spock, scissors),
+            (scissors, lizard),
+            (lizard, paper),
+            (paper, spock),
+            (spock, rock),
+            (rock, scissors),
+        ] {
+            assert_eq!(game.outcome(winner, loser), Outcome::Win);
+            assert_eq!(game.outcome(loser, winner), Outcome::Loss);
+        }
+        assert_eq!(game.shape_for(rock, Outcome::Win), paper);
+        assert_eq!(game.shape_for(rock, Outcome::Loss), scissors);
+        assert_eq!(Game::new(4), None);
+        assert_eq!(Game::new(1), None);
+        assert_eq!(Game::new(3), Some(Game::RPS));
+    }
+
+    #[test]
+    fn best_mapping() {
+        let guide = parse_input(input()).unwrap();
+        let (shapes, score) = Game::RPS.best_mapping(&guide, Goal::Maximise).unwrap();
+        assert_eq!(score, 24);
+        assert_eq!(
+            Game::RPS.total_score(&guide, Interpretation::Shapes(shapes)),
+            24
+        );
+        assert_eq!(
+            Game::RPS.best_mapping(&guide, Goal::Minimise),
+            Some(([Shape::ROCK, Shape::SCISSORS, Shape::PAPER], 6))
+        );
+
+        let guide = Game::RPSLS.parse_guide("E X\nD Y\nE Z").unwrap();
+        let (_, score) = Game::RPSLS.best_mapping(&guide, Goal::Maximise).unwrap();
+        assert_eq!(score, 26);
+    }
+
+    #[test]
+    fn guide_errors() {
+        assert_eq!(
+            parse_input("A Y\nD X"),
+            Err(Error::InvalidShape {
+                line: 2,
+                found: "D".to_string()
+            })
+        );
+        assert_eq!(
+            parse_input("A W"),
+            Err(Error::InvalidColumn {
+                line: 1,
+                found: "W".to_string()
+            })
+        );
+        assert_eq!(parse_input("AX"), Err(Error::InvalidLine { line: 1 }));
     }
 }
@@ -1,62 +1,234 @@
-use std::collections::HashSet;
+use std::fmt::Display;
 
 use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::Itertools;
 
-type Parsed = Vec<String>;
+type Parsed = Vec<Rucksack>;
+
+// Item types as a 52-bit mask, bit `priority - 1` set for each type present.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct ItemSet(u64);
+
+impl ItemSet {
+    pub const ALL: ItemSet = ItemSet((1 << 52) - 1);
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn intersection(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+
+    pub fn priorities(self) -> impl Iterator<Item = usize> {
+        (1..=52).filter(move |priority| self.0 >> (priority - 1) & 1 == 1)
+    }
+
+    pub fn items(self) -> impl Iterator<Item = char> {
+        self.priorities().map(item)
+    }
+}
+
+impl FromIterator<usize> for ItemSet {
+    fn from_iter<T: IntoIterator<Item = usize>>(priorities: T) -> Self {
+        ItemSet(
+            priorities
+                .into_iter()
+                .fold(0, |mask, priority| mask | 1 << (priority - 1)),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    InvalidItem {
+        line: usize,
+        position: usize,
+        found: char,
+    },
+    UnevenCompartments {
+        line: usize,
+        items: usize,
+        compartments: usize,
+    },
+    InvalidGroupSize {
+        size: usize,
+    },
+    IncompleteGroup {
+        lines: Vec<usize>,
+        size: usize,
+    },
+    NoCommonItem {
+        lines: Vec<usize>,
+    },
+    SeveralCommonItems {
+        lines: Vec<usize>,
+        items: Vec<char>,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = |lines: &[usize]| lines.iter().join(", ");
+        match self {
+            Error::InvalidItem {
+                line,
+                position,
+                found,
+            } => write!(
+                f,
+                "line {line}: invalid item '{found}' at position {position}"
+            ),
+            Error::UnevenCompartments {
+                line,
+                items,
+                compartments,
+            } => write!(
+                f,
+                "line {line}: {items} items can't be split into {compartments} compartments"
+            ),
+            Error::InvalidGroupSize { size } => write!(f, "invalid group size {size}"),
+            Error::IncompleteGroup { lines: l, size } => {
+                write!(
+                    f,
+                    "lines {}: group has fewer than {size} rucksacks",
+                    lines(l)
+                )
+            }
+            Error::NoCommonItem { lines: l } => write!(f, "lines {}: no common item", lines(l)),
+            Error::SeveralCommonItems { lines: l, items } => write!(
+                f,
+                "lines {}: several common items {}",
+                lines(l),
+                items.iter().collect::<String>()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Item sets of a whole rucksack and of each of its compartments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rucksack {
+    pub line: usize,
+    pub items: ItemSet,
+    pub compartments: Vec<ItemSet>,
+}
+
+impl Rucksack {
+    pub fn parse(line: usize, items: &str, compartments: usize) -> Result<Self, Error> {
+        let priorities: Vec<usize> = items
+            .chars()
+            .enumerate()
+            .map(|(position, found)| {
+                item_priority(found).ok_or(Error::InvalidItem {
+                    line,
+                    position: position + 1,
+                    found,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        if compartments == 0 || !priorities.len().is_multiple_of(compartments) {
+            return Err(Error::UnevenCompartments {
+                line,
+                items: priorities.len(),
+                compartments,
+            });
+        }
+
+        Ok(Rucksack {
+            line,
+            items: priorities.iter().copied().collect(),
+            compartments: priorities
+                .chunks((priorities.len() / compartments).max(1))
+                .map(|compartment| compartment.iter().copied().collect())
+                .collect(),
+        })
+    }
+}
+
+pub fn parse_rucksacks(input: &str, compartments: usize) -> Result<Vec<Rucksack>, Error> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| Rucksack::parse(i + 1, line, compartments))
+        .collect()
+}
 
 #[aoc_generator(day3)]
-fn parse_input(input: &str) -> Parsed {
-    input.lines().map(|line| String::from(line)).collect()
+fn parse_input(input: &str) -> Result<Parsed, Error> {
+    parse_rucksacks(input, 2)
+}
+
+pub fn item_priority(item: char) -> Option<usize> {
+    match item {
+        'a'..='z' => Some(item as usize - 'a' as usize + 1),
+        'A'..='Z' => Some(item as usize - 'A' as usize + 27),
+        _ => None,
+    }
 }
 
-// Did only work for part 1 :(
-// fn common_item((left, right): &(String, String)) -> char {
-//     let left = left.chars().collect::<HashSet<char>>();
-//     let right = right.chars().collect::<HashSet<char>>();
-//     return left.intersection(&right).next().unwrap().to_owned();
-// }
+fn item(priority: usize) -> char {
+    match priority {
+        1..=26 => (b'a' + priority as u8 - 1) as char,
+        _ => (b'A' + priority as u8 - 27) as char,
+    }
+}
 
-fn common_item(item_lists: Vec<String>) -> char {
-    item_lists
+// Priority of the single item type shared by all sets.
+pub fn common_item(sets: &[ItemSet], lines: &[usize]) -> Result<usize, Error> {
+    let common = sets
         .iter()
-        .map(|list| list.chars().collect::<HashSet<char>>())
-        .reduce(|acc, list| {
-            acc.intersection(&list)
-                .map(|char| char.clone())
-                .collect::<HashSet<char>>()
-        })
-        .unwrap()
+        .fold(ItemSet::ALL, |common, set| common.intersection(*set));
+    match common.len() {
+        1 => Ok(common.priorities().next().unwrap()),
+        0 => Err(Error::NoCommonItem {
+            lines: lines.to_vec(),
+        }),
+        _ => Err(Error::SeveralCommonItems {
+            lines: lines.to_vec(),
+            items: common.items().collect(),
+        }),
+    }
+}
+
+pub fn compartment_priorities(rucksacks: &[Rucksack]) -> Result<usize, Error> {
+    rucksacks
         .iter()
-        .next()
-        .unwrap()
-        .to_owned()
+        .map(|rucksack| common_item(&rucksack.compartments, &[rucksack.line]))
+        .sum()
 }
 
-fn item_priority(item: char) -> usize {
-    let ascii = item as u8;
-    if ascii >= 97 {
-        (ascii - 96) as usize
-    } else {
-        (ascii - 64 + 26) as usize
+pub fn group_priorities(rucksacks: &[Rucksack], size: usize) -> Result<usize, Error> {
+    if size == 0 {
+        return Err(Error::InvalidGroupSize { size });
     }
+    rucksacks
+        .chunks(size)
+        .map(|group| {
+            let lines = group.iter().map(|rucksack| rucksack.line).collect_vec();
+            if group.len() < size {
+                return Err(Error::IncompleteGroup { lines, size });
+            }
+            let sets = group.iter().map(|rucksack| rucksack.items).collect_vec();
+            common_item(&sets, &lines)
+        })
+        .sum()
 }
 
 #[aoc(day3, part1)]
-fn part1(input: &Parsed) -> usize {
-    input
-        .iter()
-        .map(|line| line.split_at(line.len() / 2))
-        .map(|str| (str.0.to_string(), str.1.to_string()))
-        .map(|rucksack| item_priority(common_item(vec![rucksack.0.clone(), rucksack.1.clone()])))
-        .sum()
+fn part1(input: &Parsed) -> Result<usize, Error> {
+    compartment_priorities(input)
 }
 
 #[aoc(day3, part2)]
-fn part2(input: &Parsed) -> usize {
-    input
-        .chunks(3)
-        .map(|chunk| item_priority(common_item(chunk.to_vec())))
-        .sum()
+fn part2(input: &Parsed) -> Result<usize, Error> {
+    group_priorities(input, 3)
 }
 
 #[cfg(test)]
@@ -74,11 +246,105 @@ CrZsJsPPZsGzwwsLwLmpwMDw"
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&parse_input(input())), 157);
+        assert_eq!(part1(&parse_input(input()).unwrap()), Ok(157));
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&parse_input(input())), 70);
+        assert_eq!(part2(&parse_input(input()).unwrap()), Ok(70));
+    }
+
+    #[test]
+    fn item_sets() {
+        let rucksack = Rucksack::parse(1, "aAzZa", 1).unwrap();
+        assert_eq!(rucksack.items.len(), 4);
+        assert_eq!(
+            rucksack.items.items().collect::<String>(),
+            "azAZ".to_string()
+        );
+        assert_eq!(
+            rucksack.items.priorities().collect_vec(),
+            vec![1, 26, 27, 52]
+        );
+        assert_eq!(rucksack.compartments, vec![rucksack.items]);
+    }
+
+    #[test]
+    fn other_sizes() {
+        let rucksacks = parse_rucksacks("abcxyz\nbcaqrs\nsxbuvw\nbtuvwx", 1).unwrap();
+        assert_eq!(group_priorities(&rucksacks, 4), Ok(2));
+        assert_eq!(
+            group_priorities(&rucksacks, 1),
+            Err(Error::SeveralCommonItems {
+                lines: vec![1],
+                items: vec!['a', 'b', 'c', 'x', 'y', 'z'],
+            })
+        );
+        let rucksacks = parse_rucksacks("abcabcabc\nxyzxyzxyz", 3).unwrap();
+        assert_eq!(
+            compartment_priorities(&rucksacks),
+            Err(Error::SeveralCommonItems {
+                lines: vec![1],
+                items: vec!['a', 'b', 'c'],
+            })
+        );
+        let rucksacks = parse_rucksacks("aAbaBcaCd", 3).unwrap();
+        assert_eq!(compartment_priorities(&rucksacks), Ok(1));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_rucksacks("abc\nab1c", 1),
+            Err(Error::InvalidItem {
+                line: 2,
+                position: 3,
+                found: '1'
+            })
+        );
+
+        assert_eq!(
+            parse_rucksacks(input(), 5),
+            Err(Error::UnevenCompartments {
+                line: 1,
+                items: 24,
+                compartments: 5
+            })
+        );
+        assert_eq!(
+            parse_rucksacks("ab", 0),
+            Err(Error::UnevenCompartments {
+                line: 1,
+                items: 2,
+                compartments: 0
+            })
+        );
+
+        let rucksacks = parse_input(input()).unwrap();
+        assert_eq!(
+            group_priorities(&rucksacks, 0),
+            Err(Error::InvalidGroupSize { size: 0 })
+        );
+        assert_eq!(
+            group_priorities(&rucksacks[..4], 3),
+            Err(Error::IncompleteGroup {
+                lines: vec![4],
+                size: 3
+            })
+        );
+        assert_eq!(
+            parse_rucksacks("abc\nxyz\nabz", 1)
+                .and_then(|rucksacks| group_priorities(&rucksacks, 3)),
+            Err(Error::NoCommonItem {
+                lines: vec![1, 2, 3]
+            })
+        );
+        assert_eq!(
+            parse_input("ab\nab").and_then(|rucksacks| group_priorities(&rucksacks, 2)),
+            Err(Error::SeveralCommonItems {
+                lines: vec![1, 2],
+                items: vec!['a', 'b']
+            })
+        );
     }
 }
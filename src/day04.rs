@@ -1,9 +1,9 @@
-use std::ops::RangeInclusive;
-
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 
-type Parsed = Vec<(RangeInclusive<u32>, RangeInclusive<u32>)>;
+use crate::interval::Interval;
+
+type Parsed = Vec<(Interval<u32>, Interval<u32>)>;
 
 #[aoc_generator(day4)]
 fn parse_input(input: &str) -> Parsed {
@@ -16,29 +16,14 @@ fn parse_input(input: &str) -> Parsed {
                 .map(|number| number.parse().unwrap())
                 .collect_tuple::<(u32, u32, u32, u32)>()
                 .unwrap();
-            (limits.0..=limits.1, limits.2..=limits.3)
+            (
+                Interval::inclusive(limits.0, limits.1),
+                Interval::inclusive(limits.2, limits.3),
+            )
         })
         .collect()
 }
 
-trait FullyContains<T> {
-    fn fully_contains(&self, other: &T) -> bool;
-    fn overlaps(&self, other: &T) -> bool;
-}
-
-impl FullyContains<RangeInclusive<u32>> for RangeInclusive<u32> {
-    fn fully_contains(&self, other: &RangeInclusive<u32>) -> bool {
-        self.contains(&other.start()) && self.contains(&other.end())
-    }
-
-    fn overlaps(&self, other: &RangeInclusive<u32>) -> bool {
-        self.contains(&other.start())
-            || self.contains(&other.end())
-            || other.contains(&self.start())
-            || other.contains(&self.end())
-    }
-}
-
 #[aoc(day4, part1)]
 fn part1(input: &Parsed) -> usize {
     input
@@ -1,97 +1,508 @@
-use std::collections::LinkedList;
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    ops::Range,
+};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
-use regex::Regex;
 
-type Stack = LinkedList<char>;
-type Parsed = (Vec<Stack>, Vec<Operation>);
+// Crates from bottom to top.
+pub type Stack = Vec<String>;
+type Parsed = (Drawing, Vec<Operation>);
 
-struct Operation {
-    from: usize,
-    to: usize,
-    count: usize,
+// Stacks are indexed from zero into the drawing; `Drawing::describe` shows
+// them by label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operation {
+    pub from: usize,
+    pub to: usize,
+    pub count: usize,
 }
 
-#[aoc_generator(day5)]
-fn parse_input(input: &str) -> Parsed {
-    let (stacks, ops) = input.split("\n\n").collect_tuple::<(&str, &str)>().unwrap();
-    let mut lines = stacks.lines().collect_vec();
-    let stack_count = lines
-        .pop()
-        .unwrap()
-        .split("   ")
-        .last()
-        .unwrap()
-        .trim()
-        .parse::<usize>()
-        .unwrap();
-    let mut stacks: Vec<Stack> = vec![LinkedList::new(); stack_count];
-    lines.iter().for_each(|line| {
-        line.chars()
-            .chunks(4)
-            .into_iter()
-            .enumerate()
-            .for_each(|(index, item)| {
-                let item = item.collect::<Vec<char>>()[1];
-                if item != ' ' {
-                    stacks[index].push_back(item);
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drawing {
+    // one label per stack
+    labels: Vec<String>,
+    stacks: Vec<Stack>,
+}
+
+impl Drawing {
+    pub fn new(labels: Vec<String>, stacks: Vec<Stack>) -> Option<Self> {
+        (labels.len() == stacks.len()).then_some(Self { labels, stacks })
+    }
+
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    pub fn stacks(&self) -> &[Stack] {
+        &self.stacks
+    }
+
+    pub fn stack_index(&self, label: &str) -> Option<usize> {
+        self.labels.iter().position(|l| l == label)
+    }
+
+    // The operation as written in the puzzle input, which `parse_operation`
+    // reads back.
+    pub fn describe(&self, operation: &Operation) -> Option<String> {
+        Some(format!(
+            "move {} from {} to {}",
+            operation.count,
+            self.labels.get(operation.from)?,
+            self.labels.get(operation.to)?
+        ))
+    }
+}
+
+// Columns are as wide as their widest crate or label, with labels and crates
+// centred, so the output parses back into the same drawing.
+impl Display for Drawing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let widths = self
+            .labels
+            .iter()
+            .zip(&self.stacks)
+            .map(|(label, stack)| {
+                let widest_crate = stack.iter().map(|name| name.chars().count() + 2);
+                widest_crate.chain([label.chars().count()]).max().unwrap()
+            })
+            .collect_vec();
+        let height = self
+            .stacks
+            .iter()
+            .map(|stack| stack.len())
+            .max()
+            .unwrap_or(0);
+
+        let rows = (0..height).rev().map(|level| {
+            self.stacks
+                .iter()
+                .zip(&widths)
+                .map(|(stack, width)| match stack.get(level) {
+                    Some(name) => format!("{:^width$}", format!("[{name}]")),
+                    None => " ".repeat(*width),
+                })
+                .join(" ")
+        });
+        let labels = self
+            .labels
+            .iter()
+            .zip(&widths)
+            .map(|(label, width)| format!("{label:^width$}"))
+            .join(" ");
+
+        write!(f, "{}", rows.chain([labels]).join("\n"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    MissingLabels,
+    DuplicateLabel(String),
+    UnexpectedChar(char),
+    UnclosedCrate,
+    EmptyCrate,
+    UnalignedCrate,
+    AmbiguousCrate,
+    FloatingCrate,
+    InvalidOperation,
+    UnknownStack(String),
+}
+
+// Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ParseErrorKind::MissingLabels => write!(f, "expected a line of stack labels"),
+            ParseErrorKind::DuplicateLabel(label) => write!(f, "duplicate stack label {label}"),
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {c:?}"),
+            ParseErrorKind::UnclosedCrate => write!(f, "crate is missing its ']'"),
+            ParseErrorKind::EmptyCrate => write!(f, "crate has no name"),
+            ParseErrorKind::UnalignedCrate => write!(f, "crate is not above any stack label"),
+            ParseErrorKind::AmbiguousCrate => write!(f, "crate is above several stack labels"),
+            ParseErrorKind::FloatingCrate => write!(f, "crate has nothing below it"),
+            ParseErrorKind::InvalidOperation => {
+                write!(f, "expected 'move <count> from <stack> to <stack>'")
+            }
+            ParseErrorKind::UnknownStack(label) => write!(f, "there is no stack {label}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Whitespace separated words with the character columns they span.
+fn words(line: &str) -> Vec<(Range<usize>, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (column, (offset, c)) in line.char_indices().chain([(line.len(), ' ')]).enumerate() {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some((column, offset)),
+            (Some((start_column, start_offset)), true) => {
+                words.push((start_column..column, &line[start_offset..offset]));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    words
+}
+
+// `[name]` crates with the character columns they span, brackets included.
+fn crates(line: &str, line_number: usize) -> Result<Vec<(Range<usize>, String)>, ParseError> {
+    let error = |column: usize, kind| ParseError {
+        line: line_number,
+        column: column + 1,
+        kind,
+    };
+    let chars = line.chars().collect_vec();
+    let mut crates = Vec::new();
+    let mut column = 0;
+    while column < chars.len() {
+        match chars[column] {
+            c if c.is_whitespace() => column += 1,
+            '[' => {
+                let close = chars[column..]
+                    .iter()
+                    .position(|c| *c == ']')
+                    .ok_or_else(|| error(column, ParseErrorKind::UnclosedCrate))?;
+                let name = chars[column + 1..column + close].iter().collect::<String>();
+                if name.is_empty() || name.chars().any(char::is_whitespace) {
+                    return Err(error(column, ParseErrorKind::EmptyCrate));
                 }
+                crates.push((column..column + close + 1, name));
+                column += close + 1;
+            }
+            c => return Err(error(column, ParseErrorKind::UnexpectedChar(c))),
+        }
+    }
+    Ok(crates)
+}
+
+// Reads the drawing from its label line upwards, placing every crate on the
+// stack whose label it overlaps.
+pub fn parse_drawing(lines: &[&str]) -> Result<Drawing, ParseError> {
+    let missing_labels = ParseError {
+        line: lines.len().max(1),
+        column: 1,
+        kind: ParseErrorKind::MissingLabels,
+    };
+    let (label_line, crate_lines) = lines.split_last().ok_or(missing_labels.clone())?;
+    let label_words = words(label_line);
+    if label_words.is_empty() {
+        return Err(missing_labels);
+    }
+
+    let mut seen = HashSet::new();
+    for (span, label) in &label_words {
+        if !seen.insert(*label) {
+            return Err(ParseError {
+                line: lines.len(),
+                column: span.start + 1,
+                kind: ParseErrorKind::DuplicateLabel(label.to_string()),
             });
-    });
+        }
+    }
 
-    let re = Regex::new(r"^move (\d+) from (\d+) to (\d+)").unwrap();
-    let operations = ops
-        .lines()
-        .map(|line| {
-            let matches = re.captures(line).unwrap();
-            Operation {
-                count: matches[1].parse().unwrap(),
-                from: matches[2].parse::<usize>().unwrap() - 1,
-                to: matches[3].parse::<usize>().unwrap() - 1,
+    let mut stacks = vec![Stack::new(); label_words.len()];
+    for (level, (index, line)) in crate_lines.iter().enumerate().rev().enumerate() {
+        let line_number = index + 1;
+        for (span, name) in crates(line, line_number)? {
+            let error = |kind| ParseError {
+                line: line_number,
+                column: span.start + 1,
+                kind,
+            };
+            let below = label_words
+                .iter()
+                .positions(|(label, _)| label.start < span.end && span.start < label.end)
+                .collect_vec();
+            let stack = match below[..] {
+                [stack] => stack,
+                [] => return Err(error(ParseErrorKind::UnalignedCrate)),
+                _ => return Err(error(ParseErrorKind::AmbiguousCrate)),
+            };
+            if stacks[stack].len() != level {
+                return Err(error(ParseErrorKind::FloatingCrate));
             }
-        })
-        .collect_vec();
-    (stacks, operations)
+            stacks[stack].push(name);
+        }
+    }
+
+    Ok(Drawing {
+        labels: label_words
+            .into_iter()
+            .map(|(_, label)| label.to_string())
+            .collect(),
+        stacks,
+    })
 }
 
-#[aoc(day5, part1)]
-fn part1(input: &Parsed) -> String {
-    let mut stacks = input.0.to_owned();
+pub fn parse_operation(
+    line: &str,
+    line_number: usize,
+    drawing: &Drawing,
+) -> Result<Operation, ParseError> {
+    let error = |column: usize, kind| ParseError {
+        line: line_number,
+        column: column + 1,
+        kind,
+    };
+    let invalid = |column| error(column, ParseErrorKind::InvalidOperation);
 
-    input.1.iter().for_each(|op| {
-        for _ in 0..op.count {
-            let item = stacks[op.from].pop_front().unwrap();
-            stacks[op.to].push_front(item);
-        }
-    });
+    let words = words(line);
+    let [(_, "move"), (count_span, count), (_, "from"), (from_span, from), (_, "to"), (to_span, to)] =
+        &words[..]
+    else {
+        return Err(invalid(words.first().map_or(0, |(span, _)| span.start)));
+    };
+    let stack = |span: &Range<usize>, label: &str| {
+        drawing
+            .stack_index(label)
+            .ok_or_else(|| error(span.start, ParseErrorKind::UnknownStack(label.to_string())))
+    };
+
+    Ok(Operation {
+        count: count.parse().map_err(|_| invalid(count_span.start))?,
+        from: stack(from_span, from)?,
+        to: stack(to_span, to)?,
+    })
+}
+
+#[aoc_generator(day5)]
+fn parse_input(input: &str) -> Result<Parsed, ParseError> {
+    let lines = input.lines().collect_vec();
+    let separator = lines
+        .iter()
+        .position(|line| line.trim().is_empty())
+        .unwrap_or(lines.len());
+    let drawing = parse_drawing(&lines[..separator])?;
 
-    stacks
+    let operations = lines
         .iter()
-        .map(|stack| stack.front().unwrap())
-        .collect::<String>()
+        .enumerate()
+        .skip(separator)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| parse_operation(line, index + 1, &drawing))
+        .try_collect()?;
+    Ok((drawing, operations))
 }
 
-#[aoc(day5, part2)]
-fn part2(input: &Parsed) -> String {
-    let mut stacks = input.0.to_owned();
+pub trait Crane {
+    // Sizes of the successive lifts used to move `count` crates. Each lift
+    // keeps the order of the crates it carries.
+    fn lifts(&self, count: usize) -> Vec<usize>;
+}
+
+// Moves one crate at a time.
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn lifts(&self, count: usize) -> Vec<usize> {
+        vec![1; count]
+    }
+}
+
+// Moves any number of crates at once.
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn lifts(&self, count: usize) -> Vec<usize> {
+        vec![count]
+    }
+}
+
+// Moves up to `capacity` crates at once.
+pub struct LimitedCrane {
+    capacity: usize,
+}
+
+impl LimitedCrane {
+    pub fn new(capacity: usize) -> Option<Self> {
+        (capacity > 0).then_some(Self { capacity })
+    }
+}
+
+impl Crane for LimitedCrane {
+    fn lifts(&self, count: usize) -> Vec<usize> {
+        (0..count)
+            .step_by(self.capacity)
+            .map(|lifted| self.capacity.min(count - lifted))
+            .collect()
+    }
+}
+
+// Steps are numbered from 1 in the order the operations were applied. A
+// missing stack has no label, so it is reported by index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    NoSuchStack {
+        step: usize,
+        stack: usize,
+    },
+    NotEnoughCrates {
+        step: usize,
+        stack: String,
+        requested: usize,
+        available: usize,
+    },
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::NoSuchStack { step, stack } => {
+                write!(f, "step {step}: there is no stack at index {stack}")
+            }
+            MoveError::NotEnoughCrates {
+                step,
+                stack,
+                requested,
+                available,
+            } => write!(
+                f,
+                "step {step}: can't move {requested} crates from stack {stack} holding {available}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
 
-    input.1.iter().for_each(|op| {
-        let mut items = LinkedList::new();
+pub struct CrateMachine<C: Crane> {
+    crane: C,
+    initial: Drawing,
+    stacks: Vec<Stack>,
+    log: Vec<Operation>,
+}
 
-        for _ in 0..op.count {
-            items.push_front(stacks[op.from].pop_front().unwrap());
+impl<C: Crane> CrateMachine<C> {
+    pub fn new(crane: C, drawing: Drawing) -> Self {
+        Self {
+            crane,
+            stacks: drawing.stacks.clone(),
+            initial: drawing,
+            log: Vec::new(),
         }
+    }
+
+    pub fn stacks(&self) -> &[Stack] {
+        &self.stacks
+    }
 
-        while let Some(item) = items.pop_front() {
-            stacks[op.to].push_front(item);
+    // Every operation applied so far.
+    pub fn log(&self) -> &[Operation] {
+        &self.log
+    }
+
+    pub fn describe_log(&self) -> Vec<String> {
+        self.log
+            .iter()
+            .map(|operation| self.initial.describe(operation).unwrap())
+            .collect()
+    }
+
+    fn validate(&self, operation: &Operation) -> Result<(), MoveError> {
+        let step = self.log.len() + 1;
+        for stack in [operation.from, operation.to] {
+            if stack >= self.stacks.len() {
+                return Err(MoveError::NoSuchStack { step, stack });
+            }
         }
-    });
 
-    stacks
-        .iter()
-        .map(|stack| stack.front().unwrap())
-        .collect::<String>()
+        let available = self.stacks[operation.from].len();
+        if available < operation.count {
+            return Err(MoveError::NotEnoughCrates {
+                step,
+                stack: self.initial.labels[operation.from].clone(),
+                requested: operation.count,
+                available,
+            });
+        }
+        Ok(())
+    }
+
+    // Leaves the stacks untouched if the operation is invalid.
+    pub fn apply(&mut self, operation: Operation) -> Result<(), MoveError> {
+        self.validate(&operation)?;
+        Self::transfer(&self.crane, &mut self.stacks, operation);
+        self.log.push(operation);
+        Ok(())
+    }
+
+    fn transfer(crane: &C, stacks: &mut [Stack], operation: Operation) {
+        for lift in crane.lifts(operation.count) {
+            let from = &mut stacks[operation.from];
+            let load = from.split_off(from.len() - lift);
+            stacks[operation.to].extend(load);
+        }
+    }
+
+    pub fn run<'a>(
+        &mut self,
+        operations: impl IntoIterator<Item = &'a Operation>,
+    ) -> Result<(), MoveError> {
+        operations
+            .into_iter()
+            .try_for_each(|operation| self.apply(*operation))
+    }
+
+    // One entry per stack, `None` for an empty one.
+    pub fn top_crates(&self) -> Vec<Option<&str>> {
+        self.stacks
+            .iter()
+            .map(|stack| stack.last().map(String::as_str))
+            .collect()
+    }
+
+    fn draw(&self, stacks: Vec<Stack>) -> String {
+        Drawing {
+            labels: self.initial.labels.clone(),
+            stacks,
+        }
+        .to_string()
+    }
+
+    pub fn drawing(&self) -> String {
+        self.draw(self.stacks.clone())
+    }
+
+    // The stacks after the first `step` logged operations, 0 being the
+    // starting arrangement.
+    pub fn drawing_at(&self, step: usize) -> Option<String> {
+        let mut stacks = self.initial.stacks.clone();
+        for operation in self.log.get(..step)? {
+            Self::transfer(&self.crane, &mut stacks, *operation);
+        }
+        Some(self.draw(stacks))
+    }
+}
+
+// The answer joins the top crates, skipping empty stacks.
+fn rearrange(crane: impl Crane, input: &Parsed) -> Result<String, MoveError> {
+    let mut machine = CrateMachine::new(crane, input.0.clone());
+    machine.run(&input.1)?;
+    Ok(machine.top_crates().into_iter().flatten().collect())
+}
+
+#[aoc(day5, part1)]
+fn part1(input: &Parsed) -> Result<String, MoveError> {
+    rearrange(CrateMover9000, input)
+}
+
+#[aoc(day5, part2)]
+fn part2(input: &Parsed) -> Result<String, MoveError> {
+    rearrange(CrateMover9001, input)
 }
 
 #[cfg(test)]
@@ -112,11 +523,247 @@ move 1 from 1 to 2"
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&parse_input(input())), "CMZ");
+        assert_eq!(part1(&parse_input(input()).unwrap()).unwrap(), "CMZ");
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&parse_input(input())), "MCD");
+        assert_eq!(part2(&parse_input(input()).unwrap()).unwrap(), "MCD");
+    }
+
+    #[test]
+    fn limited_crane() {
+        let crane = LimitedCrane::new(2).unwrap();
+        assert_eq!(crane.lifts(5), vec![2, 2, 1]);
+        assert_eq!(
+            rearrange(
+                LimitedCrane::new(1).unwrap(),
+                &parse_input(input()).unwrap()
+            )
+            .unwrap(),
+            "CMZ"
+        );
+        assert_eq!(
+            rearrange(crane, &parse_input(input()).unwrap()).unwrap(),
+            "MCZ"
+        );
+        assert!(LimitedCrane::new(0).is_none());
+    }
+
+    #[test]
+    fn invalid_moves() {
+        let (drawing, _) = parse_input(input()).unwrap();
+        let mut machine = CrateMachine::new(CrateMover9000, drawing);
+        let operations = [
+            Operation {
+                from: 1,
+                to: 0,
+                count: 1,
+            },
+            Operation {
+                from: 2,
+                to: 0,
+                count: 2,
+            },
+        ];
+        assert_eq!(
+            machine.run(&operations),
+            Err(MoveError::NotEnoughCrates {
+                step: 2,
+                stack: "3".to_string(),
+                requested: 2,
+                available: 1
+            })
+        );
+        assert_eq!(machine.log(), &operations[..1]);
+        assert_eq!(machine.top_crates(), vec![Some("D"), Some("C"), Some("P")]);
+        assert_eq!(
+            machine.apply(Operation {
+                from: 0,
+                to: 3,
+                count: 1
+            }),
+            Err(MoveError::NoSuchStack { step: 2, stack: 3 })
+        );
+        assert_eq!(
+            MoveError::NoSuchStack { step: 2, stack: 3 }.to_string(),
+            "step 2: there is no stack at index 3"
+        );
+    }
+
+    #[test]
+    fn drawings() {
+        let (drawing, operations) = parse_input(input()).unwrap();
+        let mut machine = CrateMachine::new(CrateMover9000, drawing);
+        machine.run(&operations).unwrap();
+
+        assert_eq!(
+            machine.drawing_at(0).unwrap(),
+            input().split("\n\n").next().unwrap()
+        );
+        assert_eq!(
+            machine.drawing_at(1).unwrap(),
+            "[D]        
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 "
+        );
+        assert_eq!(
+            machine.drawing(),
+            "        [Z]
+        [N]
+        [D]
+[C] [M] [P]
+ 1   2   3 "
+        );
+        assert_eq!(machine.drawing_at(4), Some(machine.drawing()));
+        assert_eq!(machine.drawing_at(5), None);
+        assert_eq!(machine.describe_log()[1], "move 3 from 1 to 3");
+    }
+
+    #[test]
+    fn labelled_log() {
+        let drawing = Drawing::new(
+            ["10", "2", "7"].map(String::from).to_vec(),
+            vec![vec!["A".into()], vec![], vec!["B".into(), "C".into()]],
+        )
+        .unwrap();
+        let mut machine = CrateMachine::new(CrateMover9000, drawing.clone());
+        let operation = Operation {
+            from: 2,
+            to: 1,
+            count: 2,
+        };
+        machine.apply(operation).unwrap();
+        assert_eq!(machine.describe_log(), vec!["move 2 from 7 to 2"]);
+        assert_eq!(machine.top_crates(), vec![Some("A"), Some("B"), None]);
+        assert_eq!(
+            parse_operation(&machine.describe_log()[0], 1, &drawing),
+            Ok(operation)
+        );
+
+        let error = machine
+            .apply(Operation {
+                from: 0,
+                to: 1,
+                count: 3,
+            })
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "step 2: can't move 3 crates from stack 10 holding 1"
+        );
+        assert_eq!(
+            drawing.describe(&Operation {
+                from: 3,
+                to: 0,
+                count: 1
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn many_stacks_and_long_names() {
+        let input = "                                       [Q]
+[A] [B] [C] [D] [E] [F] [G] [H] [I] [J] [K]
+ 1   2   3   4   5   6   7   8   9  10  11
+
+move 1 from 11 to 10
+move 2 from 10 to 1";
+        let (drawing, operations) = parse_input(input).unwrap();
+        assert_eq!(drawing.labels().len(), 11);
+        assert_eq!(drawing.stacks()[10], vec!["K", "Q"]);
+        assert_eq!(
+            operations[0],
+            Operation {
+                from: 10,
+                to: 9,
+                count: 1
+            }
+        );
+        assert_eq!(part1(&(drawing, operations)).unwrap(), "JBCDEFGHIK");
+
+        let input = "[gold]
+[iron]  [tin]
+ left  right
+
+move 2 from left to right";
+        let (drawing, operations) = parse_input(input).unwrap();
+        assert_eq!(drawing.labels(), vec!["left", "right"]);
+        assert_eq!(drawing.stacks(), vec![vec!["iron", "gold"], vec!["tin"]]);
+        assert_eq!(part2(&(drawing, operations)).unwrap(), "gold");
+    }
+
+    #[test]
+    fn trimmed_lines() {
+        let trimmed = input().lines().map(|line| line.trim_end()).join("\n");
+        assert_eq!(parse_input(&trimmed), parse_input(input()));
+    }
+
+    #[test]
+    fn drawing_round_trip() {
+        let (drawing, _) = parse_input(input()).unwrap();
+        assert_eq!(drawing.to_string(), input().split("\n\n").next().unwrap());
+
+        assert_eq!(Drawing::new(vec!["1".into()], vec![]), None);
+
+        let drawing = Drawing::new(
+            vec!["1".into(), "long".into(), "10".into()],
+            vec![vec!["copper".into(), "x".into()], vec![], vec!["y".into()]],
+        )
+        .unwrap();
+        assert_eq!(
+            drawing.to_string(),
+            "  [x]            
+[copper]      [y]
+   1     long 10 "
+        );
+        assert_eq!(
+            parse_drawing(&drawing.to_string().lines().collect_vec()),
+            Ok(drawing)
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |input: &str| parse_input(input).unwrap_err();
+        let at = |line, column, kind| ParseError { line, column, kind };
+
+        assert_eq!(error(""), at(1, 1, ParseErrorKind::MissingLabels));
+        assert_eq!(
+            error("[A]\n 1  1"),
+            at(2, 5, ParseErrorKind::DuplicateLabel("1".into()))
+        );
+        assert_eq!(
+            error("[A] x\n 1  2"),
+            at(1, 5, ParseErrorKind::UnexpectedChar('x'))
+        );
+        assert_eq!(error("[A\n 1"), at(1, 1, ParseErrorKind::UnclosedCrate));
+        assert_eq!(error("[]\n 1"), at(1, 1, ParseErrorKind::EmptyCrate));
+        assert_eq!(
+            error("    [A]\n 1"),
+            at(1, 5, ParseErrorKind::UnalignedCrate)
+        );
+        assert_eq!(
+            error("[LONG]\n 1 2"),
+            at(1, 1, ParseErrorKind::AmbiguousCrate)
+        );
+        assert_eq!(
+            error("[A]\n    [B]\n 1   2"),
+            at(1, 1, ParseErrorKind::FloatingCrate)
+        );
+        assert_eq!(
+            error("[A]\n 1\n\nmove one from 1 to 1"),
+            at(4, 6, ParseErrorKind::InvalidOperation)
+        );
+        assert_eq!(
+            error("[A]\n 1\n\nmove 1 from 1 to 2"),
+            at(4, 18, ParseErrorKind::UnknownStack("2".into()))
+        );
+        assert_eq!(
+            at(4, 18, ParseErrorKind::UnknownStack("2".into())).to_string(),
+            "line 4, column 18: there is no stack 2"
+        );
     }
 }
@@ -1,4 +1,7 @@
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    io::{self, BufReader, Read},
+};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
@@ -10,13 +13,82 @@ fn parse_input(input: &str) -> Parsed {
     String::from(input)
 }
 
-fn only_unique_characters(input: &[char]) -> bool {
-    let set: HashSet<_> = input.into_iter().collect();
-    set.len() == input.len()
+// Tracks where each byte was last seen, so the length of the current run of
+// distinct bytes is known after every byte in O(1).
+#[derive(Debug, Clone)]
+pub struct MarkerScanner {
+    window: usize,
+    last_seen: [Option<usize>; 256],
+    run_start: usize,
+    position: usize,
+}
+
+impl MarkerScanner {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            last_seen: [None; 256],
+            run_start: 0,
+            position: 0,
+        }
+    }
+
+    // Returns the number of bytes read so far if the last `window` of them
+    // are all different.
+    pub fn push(&mut self, byte: u8) -> Option<usize> {
+        if let Some(seen) = self.last_seen[byte as usize] {
+            self.run_start = self.run_start.max(seen + 1);
+        }
+        self.last_seen[byte as usize] = Some(self.position);
+        self.position += 1;
+
+        (self.position - self.run_start >= self.window).then_some(self.position)
+    }
+}
+
+pub fn markers(stream: &[u8], window: usize) -> impl Iterator<Item = usize> + '_ {
+    let mut scanner = MarkerScanner::new(window);
+    stream.iter().filter_map(move |byte| scanner.push(*byte))
+}
+
+pub fn find_marker(stream: &[u8], window: usize) -> Option<usize> {
+    markers(stream, window).next()
+}
+
+pub fn markers_from_reader<R: Read>(
+    reader: R,
+    window: usize,
+) -> impl Iterator<Item = io::Result<usize>> {
+    let mut scanner = MarkerScanner::new(window);
+    BufReader::new(reader)
+        .bytes()
+        .filter_map(move |byte| match byte {
+            Ok(byte) => scanner.push(byte).map(Ok),
+            Err(error) => Some(Err(error)),
+        })
+}
+
+pub fn find_marker_from_reader<R: Read>(reader: R, window: usize) -> io::Result<Option<usize>> {
+    markers_from_reader(reader, window).next().transpose()
 }
 
 #[aoc(day6, part1)]
 fn part1(input: &Parsed) -> usize {
+    find_marker(input.as_bytes(), 4).unwrap()
+}
+
+#[aoc(day6, part2)]
+fn part2(input: &Parsed) -> usize {
+    find_marker(input.as_bytes(), 14).unwrap()
+}
+
+fn only_unique_characters(input: &[char]) -> bool {
+    let set: HashSet<_> = input.iter().collect();
+    set.len() == input.len()
+}
+
+#[aoc(day6, part1, HashSet)]
+fn part1_hash_set(input: &Parsed) -> usize {
     input
         .chars()
         .tuple_windows::<(char, char, char, char)>()
@@ -34,13 +106,13 @@ fn part1(input: &Parsed) -> usize {
         + 4
 }
 
-#[aoc(day6, part2)]
-fn part2(input: &Parsed) -> usize {
+#[aoc(day6, part2, HashSet)]
+fn part2_hash_set(input: &Parsed) -> usize {
     let chars = input.chars().collect_vec();
     chars
         .windows(14)
         .enumerate()
-        .find(|(_, last_sequence)| only_unique_characters(&last_sequence))
+        .find(|(_, last_sequence)| only_unique_characters(last_sequence))
         .unwrap()
         .0
         + 14
@@ -87,4 +159,34 @@ mod tests {
         assert_eq!(part2(&parse_input(input_d())), 29);
         assert_eq!(part2(&parse_input(input_e())), 26);
     }
+
+    #[test]
+    fn sample_hash_set() {
+        for input in [input_a(), input_b(), input_c(), input_d(), input_e()] {
+            let input = parse_input(input);
+            assert_eq!(part1_hash_set(&input), part1(&input));
+            assert_eq!(part2_hash_set(&input), part2(&input));
+        }
+    }
+
+    #[test]
+    fn all_markers() {
+        assert_eq!(markers(b"aabcdeff", 3).collect_vec(), vec![4, 5, 6, 7]);
+        assert_eq!(markers(b"abcabc", 3).collect_vec(), vec![3, 4, 5, 6]);
+        assert_eq!(find_marker(b"aaaa", 2), None);
+        assert_eq!(find_marker(b"ab", 1), Some(1));
+        assert_eq!(find_marker(input_a().as_bytes(), 26), None);
+    }
+
+    #[test]
+    fn reader() {
+        assert_eq!(
+            find_marker_from_reader(input_d().as_bytes(), 14).unwrap(),
+            Some(29)
+        );
+        let all = markers_from_reader(input_a().as_bytes(), 4)
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(all, markers(input_a().as_bytes(), 4).collect_vec());
+    }
 }
@@ -1,7 +1,11 @@
-use std::slice::Iter;
+use std::{
+    io::{self, Write},
+    slice::Iter,
+};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
+use ndarray::{s, Array2, Array3, ArrayView1, ArrayView2, ArrayViewMut1, ArrayViewMut2, Axis, Zip};
 use rayon::prelude::*;
 
 struct Parsed(Vec<Vec<u8>>);
@@ -14,10 +18,10 @@ impl Parsed {
         self.0.len()
     }
 
-    fn row(&self, y: usize) -> Iter<u8> {
+    fn row(&self, y: usize) -> Iter<'_, u8> {
         self.0[y].iter()
     }
-    fn col(&self, x: usize) -> ParsedColumnIter {
+    fn col(&self, x: usize) -> ParsedColumnIter<'_> {
         ParsedColumnIter {
             parsed: self.to_owned(),
             column: x,
@@ -56,8 +60,8 @@ impl Parsed {
             .row(y)
             .take(x)
             .rev()
-            .take_while(|i| **i < value)
-            .count();
+            .position(|i| *i >= value)
+            .map_or(x, |v| v + 1);
         let right = self
             .row(y)
             .skip(x + 1)
@@ -69,10 +73,146 @@ impl Parsed {
             .rev()
             .position(|i| i >= value)
             .map_or(y, |v| v + 1);
-        let bottom = self.col(x).skip(y + 1).take_while(|i| *i < value).count();
+        let bottom = self
+            .col(x)
+            .skip(y + 1)
+            .position(|i| i >= value)
+            .map_or(self.height() - y - 1, |v| v + 1);
 
         left * right * top * bottom
     }
+
+    fn heights(&self) -> Array2<u8> {
+        Array2::from_shape_vec(
+            (self.height(), self.width()),
+            self.0.iter().flatten().copied().collect(),
+        )
+        .unwrap()
+    }
+
+    fn analyse(&self) -> ForestAnalysis {
+        ForestAnalysis::new(self.heights().view())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Left,
+        Direction::Right,
+        Direction::Up,
+        Direction::Down,
+    ];
+}
+
+// Per-tree data for every direction, indexed as `[direction, y, x]`.
+// `visible_from[[d, y, x]]` tells whether the tree can be seen from the edge
+// in direction `d`, `viewing_distance[[d, y, x]]` how far the tree itself can
+// see towards that edge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForestAnalysis {
+    pub heights: Array2<u8>,
+    pub visible_from: Array3<bool>,
+    pub viewing_distance: Array3<usize>,
+}
+
+impl ForestAnalysis {
+    pub fn new(heights: ArrayView2<u8>) -> Self {
+        let (height, width) = heights.dim();
+        let mut visible_from = Array3::from_elem((4, height, width), false);
+        let mut viewing_distance = Array3::zeros((4, height, width));
+
+        for (d, direction) in Direction::ALL.iter().enumerate() {
+            let visible = visible_from.index_axis_mut(Axis(0), d);
+            let distance = viewing_distance.index_axis_mut(Axis(0), d);
+            scan_direction(*direction, heights, visible, distance);
+        }
+
+        Self {
+            heights: heights.to_owned(),
+            visible_from,
+            viewing_distance,
+        }
+    }
+
+    pub fn visible(&self) -> Array2<bool> {
+        self.visible_from
+            .map_axis(Axis(0), |directions| directions.iter().any(|v| *v))
+    }
+
+    pub fn scenic_scores(&self) -> Array2<usize> {
+        self.viewing_distance
+            .map_axis(Axis(0), |distances| distances.product())
+    }
+
+    pub fn best_scenic_spot(&self) -> Option<((usize, usize), usize)> {
+        self.scenic_scores()
+            .indexed_iter()
+            .max_by_key(|(_, score)| **score)
+            .map(|((y, x), score)| ((x, y), *score))
+    }
+}
+
+fn scan_direction(
+    direction: Direction,
+    heights: ArrayView2<u8>,
+    visible: ArrayViewMut2<bool>,
+    distance: ArrayViewMut2<usize>,
+) {
+    // Lanes are always scanned from the edge the trees are looked at from.
+    let (heights, mut visible, mut distance) = match direction {
+        Direction::Left | Direction::Up => (heights, visible, distance),
+        Direction::Right => (
+            heights.slice_move(s![.., ..;-1]),
+            visible.slice_move(s![.., ..;-1]),
+            distance.slice_move(s![.., ..;-1]),
+        ),
+        Direction::Down => (
+            heights.slice_move(s![..;-1, ..]),
+            visible.slice_move(s![..;-1, ..]),
+            distance.slice_move(s![..;-1, ..]),
+        ),
+    };
+
+    match direction {
+        Direction::Left | Direction::Right => Zip::from(heights.rows())
+            .and(visible.rows_mut())
+            .and(distance.rows_mut())
+            .for_each(scan_lane),
+        Direction::Up | Direction::Down => Zip::from(heights.columns())
+            .and(visible.columns_mut())
+            .and(distance.columns_mut())
+            .for_each(scan_lane),
+    }
+}
+
+// Running maximum for visibility and a monotonic stack of blocking trees for
+// the viewing distance, so each lane takes linear time.
+fn scan_lane(
+    heights: ArrayView1<u8>,
+    mut visible: ArrayViewMut1<bool>,
+    mut distance: ArrayViewMut1<usize>,
+) {
+    let mut tallest = None;
+    let mut blockers: Vec<usize> = vec![];
+
+    for (i, height) in heights.iter().enumerate() {
+        visible[i] = tallest.is_none_or(|tallest| *height > tallest);
+        tallest = tallest.max(Some(*height));
+
+        while blockers.last().is_some_and(|j| heights[*j] < *height) {
+            blockers.pop();
+        }
+        distance[i] = blockers.last().map_or(i, |j| i - j);
+        blockers.push(i);
+    }
 }
 
 struct ParsedColumnIter<'a> {
@@ -115,6 +255,125 @@ impl DoubleEndedIterator for ParsedColumnIter<'_> {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapLayer {
+    Height,
+    Visible,
+    VisibleFrom(Direction),
+    ScenicScore,
+}
+
+// RGB image with one cell per tree. The best scenic spot is kept as a marker
+// so every output format can highlight it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heatmap {
+    pub colors: Array2<[u8; 3]>,
+    pub marker: Option<(usize, usize)>,
+}
+
+const MARKER_COLOR: [u8; 3] = [255, 0, 0];
+const HIDDEN_COLOR: [u8; 3] = [40, 40, 40];
+const VISIBLE_COLOR: [u8; 3] = [80, 200, 90];
+const GRADIENT: [[u8; 3]; 4] = [[20, 20, 80], [40, 120, 170], [120, 200, 90], [250, 230, 60]];
+
+fn gradient(value: f64) -> [u8; 3] {
+    let position = value.clamp(0.0, 1.0) * (GRADIENT.len() - 1) as f64;
+    let index = (position as usize).min(GRADIENT.len() - 2);
+    let t = position - index as f64;
+
+    let (from, to) = (GRADIENT[index], GRADIENT[index + 1]);
+    [0, 1, 2].map(|c| (from[c] as f64 + (to[c] as f64 - from[c] as f64) * t).round() as u8)
+}
+
+fn boolean_color(value: bool) -> [u8; 3] {
+    if value {
+        VISIBLE_COLOR
+    } else {
+        HIDDEN_COLOR
+    }
+}
+
+impl ForestAnalysis {
+    pub fn heatmap(&self, layer: HeatmapLayer) -> Heatmap {
+        let colors = match layer {
+            HeatmapLayer::Height => self.heights.mapv(|h| gradient(h as f64 / 9.0)),
+            HeatmapLayer::Visible => self.visible().mapv(boolean_color),
+            HeatmapLayer::VisibleFrom(direction) => {
+                let d = Direction::ALL.iter().position(|d| *d == direction).unwrap();
+                self.visible_from.index_axis(Axis(0), d).mapv(boolean_color)
+            }
+            HeatmapLayer::ScenicScore => {
+                let scores = self.scenic_scores();
+                let max = scores.iter().max().copied().unwrap_or(0).max(1) as f64;
+                scores.mapv(|score| gradient(score as f64 / max))
+            }
+        };
+
+        Heatmap {
+            colors,
+            marker: self.best_scenic_spot().map(|(spot, _)| spot),
+        }
+    }
+}
+
+impl Heatmap {
+    fn color(&self, x: usize, y: usize) -> [u8; 3] {
+        if self.marker == Some((x, y)) {
+            MARKER_COLOR
+        } else {
+            self.colors[[y, x]]
+        }
+    }
+
+    // Raw RGB rows, drawing each cell as a `scale` x `scale` block.
+    fn pixels(&self, scale: usize) -> (usize, usize, Vec<u8>) {
+        let (height, width) = self.colors.dim();
+        let mut pixels = Vec::with_capacity(width * height * scale * scale * 3);
+        for y in 0..height * scale {
+            for x in 0..width * scale {
+                pixels.extend(self.color(x / scale, y / scale));
+            }
+        }
+        (width * scale, height * scale, pixels)
+    }
+
+    pub fn write_ppm(&self, mut writer: impl Write, scale: usize) -> io::Result<()> {
+        let (width, height, pixels) = self.pixels(scale);
+        write!(writer, "P6\n{width} {height}\n255\n")?;
+        writer.write_all(&pixels)
+    }
+
+    pub fn write_png(&self, writer: impl Write, scale: usize) -> Result<(), png::EncodingError> {
+        let (width, height, pixels) = self.pixels(scale);
+        let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixels)
+    }
+
+    // Two terminal cells per tree using truecolor backgrounds, with the
+    // marker drawn as `<>`.
+    pub fn to_ansi(&self) -> String {
+        let (height, width) = self.colors.dim();
+        (0..height)
+            .map(|y| {
+                let row: String = (0..width)
+                    .map(|x| {
+                        let [r, g, b] = self.color(x, y);
+                        let cell = if self.marker == Some((x, y)) {
+                            "<>"
+                        } else {
+                            "  "
+                        };
+                        format!("\x1b[48;2;{r};{g};{b}m{cell}")
+                    })
+                    .collect();
+                format!("{row}\x1b[0m\n")
+            })
+            .collect()
+    }
+}
+
 #[aoc_generator(day8)]
 fn parse_input(input: &str) -> Parsed {
     Parsed(
@@ -131,11 +390,21 @@ fn parse_input(input: &str) -> Parsed {
 
 #[aoc(day8, part1)]
 fn part1(input: &Parsed) -> usize {
+    input.analyse().visible().iter().filter(|v| **v).count()
+}
+
+#[aoc(day8, part1, Naive)]
+fn part1_naive(input: &Parsed) -> usize {
     input.num_visible()
 }
 
 #[aoc(day8, part2)]
 fn part2(input: &Parsed) -> usize {
+    input.analyse().best_scenic_spot().unwrap().1
+}
+
+#[aoc(day8, part2, Naive)]
+fn part2_naive(input: &Parsed) -> usize {
     input
         .0
         .par_iter()
@@ -151,6 +420,8 @@ fn part2(input: &Parsed) -> usize {
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
     fn input<'a>() -> &'a str {
@@ -170,4 +441,86 @@ mod tests {
     fn sample2() {
         assert_eq!(part2(&parse_input(input())), 8);
     }
+
+    #[test]
+    fn sample_analysis() {
+        let analysis = parse_input(input()).analyse();
+        assert_eq!(analysis.best_scenic_spot(), Some(((2, 3), 8)));
+        assert_eq!(
+            analysis.viewing_distance.slice(s![.., 3, 2]).to_vec(),
+            vec![2, 2, 2, 1]
+        );
+        assert_eq!(
+            analysis.visible_from.slice(s![.., 1, 1]).to_vec(),
+            vec![true, false, true, false]
+        );
+    }
+
+    // The blocking tree counts towards the viewing distance in every
+    // direction, so the middle 5 of the second row sees 1 left and 2 down.
+    #[test]
+    fn viewing_distance_includes_blocking_tree() {
+        let forest = parse_input(input());
+        assert_eq!(forest.scenic_score(2, 1), 4);
+        assert_eq!(forest.scenic_score(3, 1), 1);
+        assert_eq!(
+            forest
+                .analyse()
+                .viewing_distance
+                .slice(s![.., 1, 2])
+                .to_vec(),
+            vec![1, 2, 1, 2]
+        );
+    }
+
+    #[test]
+    fn sample_heatmap() {
+        let analysis = parse_input(input()).analyse();
+
+        let heatmap = analysis.heatmap(HeatmapLayer::ScenicScore);
+        assert_eq!(heatmap.marker, Some((2, 3)));
+        assert_eq!(heatmap.colors[[0, 0]], GRADIENT[0]);
+        assert_eq!(heatmap.color(2, 3), MARKER_COLOR);
+
+        let heatmap = analysis.heatmap(HeatmapLayer::VisibleFrom(Direction::Left));
+        assert_eq!(heatmap.colors[[1, 1]], VISIBLE_COLOR);
+        assert_eq!(heatmap.colors[[1, 2]], HIDDEN_COLOR);
+
+        let mut ppm = vec![];
+        heatmap.write_ppm(&mut ppm, 2).unwrap();
+        assert!(ppm.starts_with(b"P6\n10 10\n255\n"));
+        assert_eq!(ppm.len(), "P6\n10 10\n255\n".len() + 10 * 10 * 3);
+
+        let mut png = vec![];
+        heatmap.write_png(&mut png, 1).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+
+        let ansi = analysis.heatmap(HeatmapLayer::Height).to_ansi();
+        assert_eq!(ansi.lines().count(), 5);
+        assert_eq!(ansi.matches("<>").count(), 1);
+    }
+
+    fn check_against_oracle(forest: &Parsed) -> Result<(), TestCaseError> {
+        let analysis = forest.analyse();
+        let visible = analysis.visible();
+        let scenic_scores = analysis.scenic_scores();
+        for y in 0..forest.height() {
+            for x in 0..forest.width() {
+                prop_assert_eq!(visible[[y, x]], forest.visible(x, y));
+                prop_assert_eq!(scenic_scores[[y, x]], forest.scenic_score(x, y));
+            }
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn analysis_matches_oracle(
+            forest in (1usize..12, 1usize..12).prop_flat_map(|(width, height)| {
+                prop::collection::vec(prop::collection::vec(0u8..10, width), height)
+            })
+        ) {
+            check_against_oracle(&Parsed(forest))?;
+        }
+    }
 }
@@ -1,31 +1,42 @@
-use std::{collections::HashSet, fmt::Error, str::FromStr};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Error},
+    io::BufRead,
+    str::FromStr,
+};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use crate::streaming::{records, StreamError};
+
 type Parsed = Vec<Move>;
 
-#[derive(Debug, Clone, Copy)]
-struct Move {
-    direction: Direction,
-    steps: u32,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub direction: Direction,
+    pub steps: u32,
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
     Left,
     Up,
     Right,
     Down,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
 impl FromStr for Move {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (direction, steps) = s.split_once(" ").unwrap();
+        let (direction, steps) = s.split_once(' ').ok_or(Error)?;
         Ok(Move {
             direction: direction.parse()?,
-            steps: steps.parse().unwrap(),
+            steps: steps.parse().map_err(|_| Error)?,
         })
     }
 }
@@ -39,11 +50,31 @@ impl FromStr for Direction {
             "U" => Ok(Direction::Up),
             "R" => Ok(Direction::Right),
             "D" => Ok(Direction::Down),
+            "UL" => Ok(Direction::UpLeft),
+            "UR" => Ok(Direction::UpRight),
+            "DL" => Ok(Direction::DownLeft),
+            "DR" => Ok(Direction::DownRight),
             _ => Err(Error),
         }
     }
 }
 
+impl Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Left => "L",
+            Direction::Up => "U",
+            Direction::Right => "R",
+            Direction::Down => "D",
+            Direction::UpLeft => "UL",
+            Direction::UpRight => "UR",
+            Direction::DownLeft => "DL",
+            Direction::DownRight => "DR",
+        };
+        write!(f, "{direction} {}", self.steps)
+    }
+}
+
 impl Direction {
     fn offset(&self) -> (i32, i32) {
         match self {
@@ -51,115 +82,194 @@ impl Direction {
             Direction::Right => (1, 0),
             Direction::Up => (0, 1),
             Direction::Down => (0, -1),
+            Direction::UpLeft => (-1, 1),
+            Direction::UpRight => (1, 1),
+            Direction::DownLeft => (-1, -1),
+            Direction::DownRight => (1, -1),
         }
     }
 }
 
+pub fn moves<R: BufRead>(reader: R) -> impl Iterator<Item = Result<Move, StreamError<Error>>> {
+    records(reader)
+}
+
 #[aoc_generator(day9)]
-fn parse_input(input: &str) -> Parsed {
-    input.lines().map(|line| line.parse().unwrap()).collect()
+fn parse_input(input: &str) -> Result<Parsed, StreamError<Error>> {
+    moves(input.as_bytes()).collect()
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Rope {
-    head: (i32, i32),
-    tail: (i32, i32),
+pub type Trails<const N: usize> = [HashSet<(i32, i32)>; N];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rope<const N: usize> {
+    pub knots: [(i32, i32); N],
+}
+
+impl<const N: usize> Default for Rope<N> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl Rope {
-    fn new() -> Rope {
-        Rope {
-            head: (0, 0),
-            tail: (0, 0),
+impl<const N: usize> Rope<N> {
+    pub fn new() -> Self {
+        Rope { knots: [(0, 0); N] }
+    }
+
+    pub fn step(&mut self, direction: Direction) {
+        let offset = direction.offset();
+        self.knots[0] = (self.knots[0].0 + offset.0, self.knots[0].1 + offset.1);
+
+        for i in 1..N {
+            self.knots[i] = follow(self.knots[i - 1], self.knots[i]);
         }
     }
 
-    fn apply_move(&mut self, movement: Move) -> HashSet<(i32, i32)> {
-        let mut visited = HashSet::new();
+    pub fn apply_move(&mut self, movement: Move, trails: &mut Trails<N>) {
         for _ in 0..movement.steps {
-            self.move_in_dir(movement.direction);
-            visited.insert(self.tail);
+            self.step(movement.direction);
+            for (trail, knot) in trails.iter_mut().zip(self.knots) {
+                trail.insert(knot);
+            }
         }
-        visited
     }
 
-    fn move_in_dir(&mut self, direction: Direction) {
-        let offset = direction.offset();
-        self.head = (self.head.0 + offset.0, self.head.1 + offset.1);
+    // Cells visited by every knot, including the starting position.
+    pub fn trails(&mut self, moves: &[Move]) -> Trails<N> {
+        let mut trails = self.knots.map(|knot| HashSet::from([knot]));
+        for movement in moves {
+            self.apply_move(*movement, &mut trails);
+        }
+        trails
+    }
 
-        self.update_tail();
-        // println!("{:?}, {:?}", self.head, self.tail);
+    // Like `trails`, but reads the moves one line at a time.
+    pub fn trails_streaming<R: BufRead>(
+        &mut self,
+        reader: R,
+    ) -> Result<Trails<N>, StreamError<Error>> {
+        let mut trails = self.knots.map(|knot| HashSet::from([knot]));
+        for movement in moves(reader) {
+            self.apply_move(movement?, &mut trails);
+        }
+        Ok(trails)
     }
+}
 
-    fn update_tail(&mut self) {
-        if self.distance() > 1 {
-            if self.head.0 < self.tail.0 {
-                self.tail.0 -= 1;
-            } else if self.head.0 > self.tail.0 {
-                self.tail.0 += 1;
-            }
-            if self.head.1 < self.tail.1 {
-                self.tail.1 -= 1;
-            } else if self.head.1 > self.tail.1 {
-                self.tail.1 += 1;
-            }
+fn follow(head: (i32, i32), tail: (i32, i32)) -> (i32, i32) {
+    let (dx, dy) = (head.0 - tail.0, head.1 - tail.1);
+    if dx.abs().max(dy.abs()) > 1 {
+        (tail.0 + dx.signum(), tail.1 + dy.signum())
+    } else {
+        tail
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub min: (i32, i32),
+    pub max: (i32, i32),
+}
+
+impl Bounds {
+    pub fn around<'a>(points: impl IntoIterator<Item = &'a (i32, i32)>) -> Self {
+        points.into_iter().fold(
+            Bounds {
+                min: (0, 0),
+                max: (0, 0),
+            },
+            |bounds, point| Bounds {
+                min: (bounds.min.0.min(point.0), bounds.min.1.min(point.1)),
+                max: (bounds.max.0.max(point.0), bounds.max.1.max(point.1)),
+            },
+        )
+    }
+
+    // Rows from top to bottom, with `y` pointing up as in the puzzle.
+    fn render(&self, cell: impl Fn((i32, i32)) -> char) -> String {
+        (self.min.1..=self.max.1)
+            .rev()
+            .map(|y| {
+                let row: String = (self.min.0..=self.max.0).map(|x| cell((x, y))).collect();
+                row + "\n"
+            })
+            .collect()
+    }
+}
+
+impl<const N: usize> Rope<N> {
+    fn knot_label(index: usize) -> char {
+        match index {
+            0 => 'H',
+            1 if N == 2 => 'T',
+            _ => char::from_digit(index as u32, 36).unwrap_or('?'),
         }
     }
 
-    fn distance(&self) -> i32 {
-        (self.head.0 - self.tail.0)
-            .abs()
-            .max((self.head.1 - self.tail.1).abs())
+    pub fn render(&self, bounds: Bounds) -> String {
+        bounds.render(
+            |cell| match self.knots.iter().position(|knot| *knot == cell) {
+                Some(index) => Self::knot_label(index),
+                None if cell == (0, 0) => 's',
+                None => '.',
+            },
+        )
+    }
+
+    // Every intermediate state in the puzzle's notation, sharing one bounding
+    // box so the frames line up.
+    pub fn animate(&self, moves: &[Move]) -> String {
+        let mut rope = *self;
+        let trails = rope.trails(moves);
+        let bounds = Bounds::around(trails.iter().flatten());
+
+        let mut rope = *self;
+        let mut output = format!("== Initial State ==\n\n{}", rope.render(bounds));
+        for movement in moves {
+            output += &format!("\n== {movement} ==\n");
+            for _ in 0..movement.steps {
+                rope.step(movement.direction);
+                output += &format!("\n{}", rope.render(bounds));
+            }
+        }
+        output
     }
 }
 
+pub fn render_trail(trail: &HashSet<(i32, i32)>, bounds: Bounds) -> String {
+    bounds.render(|cell| match cell {
+        (0, 0) => 's',
+        cell if trail.contains(&cell) => '#',
+        _ => '.',
+    })
+}
+
 #[aoc(day9, part1)]
 fn part1(input: &Parsed) -> usize {
-    let mut visited = HashSet::new();
-    let mut rope = Rope::new();
-    input.iter().for_each(|movement| {
-        let new_visited = rope.apply_move(*movement);
-        visited.extend(new_visited);
-    });
-    visited.len()
+    Rope::<2>::new().trails(input)[1].len()
 }
 
 #[aoc(day9, part2)]
 fn part2(input: &Parsed) -> usize {
-    let mut visited = HashSet::new();
-    let mut ropes = [Rope::new(); 9];
-    input.iter().for_each(|movement| {
-        for _ in 0..movement.steps {
-            ropes[0].move_in_dir(movement.direction);
-            for i in 1..ropes.len() {
-                ropes[i].head = ropes[i - 1].tail;
-                ropes[i].update_tail();
-            }
-            visited.insert(ropes[8].tail);
-        }
+    Rope::<10>::new().trails(input)[9].len()
+}
+
+#[aoc(day9, part2, Render)]
+fn part2_render(input: &Parsed) -> usize {
+    let mut rope = Rope::<10>::new();
+    let trails = rope.trails(input);
+    let bounds = Bounds::around(trails.iter().flatten());
+    println!("{}", rope.render(bounds));
+    println!("{}", render_trail(&trails[9], bounds));
 
-        // for y in -10..16 {
-        //     for x in -13..13 {
-        //         if ropes[0].head == (x, y) {
-        //             print!("H");
-        //         } else {
-        //             let rope = ropes.iter().skip(1).find(|r| r.tail == (x, y));
-        //             if let Some(rope) = rope {
-        //                 print!("T");
-        //             } else {
-        //                 print!(".");
-        //             }
-        //         }
-        //     }
-        //     println!("");
-        // }
-        // println!("");
-    });
-    visited.len()
+    trails[9].len()
 }
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
+
     use super::*;
 
     fn input<'a>() -> &'a str {
@@ -175,15 +285,16 @@ R 2"
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&parse_input(input())), 13);
+        assert_eq!(part1(&parse_input(input()).unwrap()), 13);
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&parse_input(input())), 1);
+        assert_eq!(part2(&parse_input(input()).unwrap()), 1);
         assert_eq!(
-            part2(&parse_input(
-                "R 5
+            part2(
+                &parse_input(
+                    "R 5
 U 8
 L 8
 D 3
@@ -191,8 +302,138 @@ R 17
 D 10
 L 25
 U 20"
-            )),
+                )
+                .unwrap()
+            ),
             36
         )
     }
+
+    #[test]
+    fn knot_trails() {
+        let trails = Rope::<10>::new().trails(&parse_input(input()).unwrap());
+        assert_eq!(trails[1].len(), 13);
+        assert_eq!(trails[9].len(), 1);
+        assert!(trails.iter().all(|trail| trail.contains(&(0, 0))));
+    }
+
+    #[test]
+    fn render_animation() {
+        let animation = Rope::<2>::new().animate(&parse_input(input()).unwrap());
+        assert!(animation.starts_with(
+            "== Initial State ==
+
+......
+......
+......
+......
+H.....
+
+== R 4 ==
+
+......
+......
+......
+......
+TH....
+
+......
+......
+......
+......
+sTH...
+"
+        ));
+        assert!(animation.ends_with(
+            "== R 2 ==
+
+......
+......
+.H....
+......
+s.....
+
+......
+......
+.TH...
+......
+s.....
+"
+        ));
+    }
+
+    #[test]
+    fn render_larger_rope() {
+        let moves = parse_input("R 5\nU 8").unwrap();
+        let mut rope = Rope::<10>::new();
+        rope.trails(&moves);
+        assert_eq!(
+            rope.render(Bounds {
+                min: (-11, -5),
+                max: (14, 15)
+            })
+            .lines()
+            .skip(7)
+            .take(8)
+            .collect_vec(),
+            vec![
+                "................H.........",
+                "................1.........",
+                "................2.........",
+                "................3.........",
+                "...............54.........",
+                "..............6...........",
+                ".............7............",
+                "............8.............",
+            ]
+        );
+    }
+
+    #[test]
+    fn render_tail_trail() {
+        let trails = Rope::<2>::new().trails(&parse_input(input()).unwrap());
+        let bounds = Bounds::around(trails.iter().flatten());
+        assert_eq!(
+            render_trail(&trails[1], bounds),
+            "..##..
+...##.
+.####.
+....#.
+s###..
+"
+        );
+    }
+
+    #[test]
+    fn diagonal_moves() {
+        let moves = parse_input("UR 3\nDL 1\nUL 2").unwrap();
+        assert_eq!(
+            moves[0],
+            Move {
+                direction: Direction::UpRight,
+                steps: 3
+            }
+        );
+
+        let mut rope = Rope::<3>::new();
+        let trails = rope.trails(&moves);
+        assert_eq!(rope.knots, [(0, 4), (1, 3), (1, 2)]);
+        assert_eq!(trails[2].len(), 3);
+        assert!("X 1".parse::<Move>().is_err());
+    }
+
+    #[test]
+    fn streaming() {
+        let trails = Rope::<10>::new()
+            .trails_streaming(input().as_bytes())
+            .unwrap();
+        assert_eq!(
+            trails,
+            Rope::<10>::new().trails(&parse_input(input()).unwrap())
+        );
+        assert!(matches!(
+            Rope::<2>::new().trails_streaming("R 4\nX 1".as_bytes()),
+            Err(StreamError::Parse { line: 2, .. })
+        ));
+    }
 }
@@ -1,100 +1,125 @@
-use std::str::FromStr;
+use std::{
+    fmt::{self, Display},
+    io::BufRead,
+    iter::repeat_n,
+    str::FromStr,
+};
 
 use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::{process_results, Itertools};
+
+use crate::streaming::{records, StreamError};
 
 type Parsed = Vec<Instruction>;
 
-#[derive(Debug, PartialEq)]
-enum Instruction {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
     NoOp,
     Add(i32),
 }
 
+#[derive(Debug, PartialEq)]
+pub struct InvalidInstruction(pub String);
+
+impl Display for InvalidInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid instruction '{}'", self.0)
+    }
+}
+
+impl std::error::Error for InvalidInstruction {}
+
 impl FromStr for Instruction {
-    type Err = ();
+    type Err = InvalidInstruction;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.starts_with("addx") {
-            Ok(Instruction::Add(
-                s.split_once(" ").unwrap().1.parse().unwrap(),
-            ))
-        } else {
-            Ok(Instruction::NoOp)
+        match s.split_once(' ') {
+            None if s == "noop" => Ok(Instruction::NoOp),
+            Some(("addx", value)) => value
+                .parse()
+                .map(Instruction::Add)
+                .map_err(|_| InvalidInstruction(s.to_string())),
+            _ => Err(InvalidInstruction(s.to_string())),
         }
     }
 }
 
+pub fn instructions<R: BufRead>(
+    reader: R,
+) -> impl Iterator<Item = Result<Instruction, StreamError<InvalidInstruction>>> {
+    records(reader)
+}
+
 #[aoc_generator(day10)]
-fn parse_input(input: &str) -> Parsed {
-    input.lines().map(|line| line.parse().unwrap()).collect()
+fn parse_input(input: &str) -> Result<Parsed, StreamError<InvalidInstruction>> {
+    instructions(input.as_bytes()).collect()
 }
 
-#[aoc(day10, part1)]
-fn part1(input: &Parsed) -> i32 {
-    let mut iter = input
+// Value of the X register during every cycle, starting with the first.
+pub fn register_values(
+    instructions: impl IntoIterator<Item = Instruction>,
+) -> impl Iterator<Item = i32> {
+    instructions
         .into_iter()
-        .scan((0, 1), |(cycle, x), instruction| {
-            match instruction {
-                Instruction::NoOp => *cycle += 1,
+        .scan(1, |x, instruction| {
+            let during = *x;
+            Some(match instruction {
+                Instruction::NoOp => repeat_n(during, 1),
                 Instruction::Add(value) => {
-                    *cycle += 2;
                     *x += value;
+                    repeat_n(during, 2)
                 }
-            };
-            Some((*cycle, *x))
+            })
         })
-        .peekable();
+        .flatten()
+}
+
+pub fn signal_strength(instructions: impl IntoIterator<Item = Instruction>) -> i32 {
+    register_values(instructions)
+        .take(220)
+        .zip(1..)
+        .filter(|(_, cycle)| cycle % 40 == 20)
+        .map(|(x, cycle)| cycle * x)
+        .sum()
+}
 
-    let mut last_x = 1;
-    [20, 60, 100, 140, 180, 220]
-        .iter()
-        .map(|tap| -> i32 {
-            while let Some((_, x)) = iter.peek().filter(|(cycle, _)| cycle < tap) {
-                last_x = *x;
-                iter.next();
+pub fn render(instructions: impl IntoIterator<Item = Instruction>) -> String {
+    register_values(instructions)
+        .take(240)
+        .zip((0..40).cycle())
+        .map(|(x, column)| {
+            if (-1..=1).contains(&(x - column)) {
+                '\u{2593}'
+            } else {
+                '\u{2591}'
             }
-            tap * last_x
         })
-        .sum()
+        .chunks(40)
+        .into_iter()
+        .map(|row| row.collect::<String>())
+        .join("\n")
+}
+
+pub fn signal_strength_streaming<R: BufRead>(
+    reader: R,
+) -> Result<i32, StreamError<InvalidInstruction>> {
+    process_results(instructions(reader), |instructions| {
+        signal_strength(instructions)
+    })
+}
+
+pub fn render_streaming<R: BufRead>(reader: R) -> Result<String, StreamError<InvalidInstruction>> {
+    process_results(instructions(reader), |instructions| render(instructions))
+}
+
+#[aoc(day10, part1)]
+fn part1(input: &Parsed) -> i32 {
+    signal_strength(input.iter().copied())
 }
 
 #[aoc(day10, part2)]
 fn part2(input: &Parsed) -> String {
-    let mut iter = input
-        .into_iter()
-        .scan((0, 1), |(cycle, x), instruction| {
-            match instruction {
-                Instruction::NoOp => *cycle += 1,
-                Instruction::Add(value) => {
-                    *cycle += 2;
-                    *x += value;
-                }
-            };
-            Some((*cycle, *x))
-        })
-        .peekable();
-
-    let mut last_x = 1;
-    (0..240)
-        .step_by(40)
-        .map(|row| {
-            (0..40)
-                .map(|col| {
-                    while let Some((_, x)) = iter.peek().filter(|(cycle, _)| cycle <= &(row + col))
-                    {
-                        last_x = *x;
-                        iter.next();
-                    }
-                    if (-1..=1).contains(&(last_x - col)) {
-                        '\u{2593}'
-                    } else {
-                        '\u{2591}'
-                    }
-                })
-                .collect::<String>()
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
+    render(input.iter().copied())
 }
 
 #[cfg(test)]
@@ -252,19 +277,19 @@ noop";
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&parse_input(INPUT)), 13140);
+        assert_eq!(part1(&parse_input(INPUT).unwrap()), 13140);
     }
 
     #[test]
     fn real_input1() {
         let input = read_to_string("input/2022/day10.txt").unwrap();
-        assert_eq!(part1(&parse_input(&input[..])), 14720);
+        assert_eq!(part1(&parse_input(&input[..]).unwrap()), 14720);
     }
 
     #[test]
     fn sample2() {
         assert_eq!(
-            part2(&parse_input(INPUT)),
+            part2(&parse_input(INPUT).unwrap()),
             "▓▓░░▓▓░░▓▓░░▓▓░░▓▓░░▓▓░░▓▓░░▓▓░░▓▓░░▓▓░░
 ▓▓▓░░░▓▓▓░░░▓▓▓░░░▓▓▓░░░▓▓▓░░░▓▓▓░░░▓▓▓░
 ▓▓▓▓░░░░▓▓▓▓░░░░▓▓▓▓░░░░▓▓▓▓░░░░▓▓▓▓░░░░
@@ -278,7 +303,7 @@ noop";
     fn real_input2() {
         let input = read_to_string("input/2022/day10.txt").unwrap();
         assert_eq!(
-            part2(&parse_input(&input[..])),
+            part2(&parse_input(&input[..]).unwrap()),
             "▓▓▓▓░▓▓▓▓░▓▓▓░░▓▓▓░░▓▓▓░░▓▓▓▓░▓▓▓▓░▓▓▓▓░
 ▓░░░░░░░▓░▓░░▓░▓░░▓░▓░░▓░▓░░░░░░░▓░▓░░░░
 ▓▓▓░░░░▓░░▓▓▓░░▓░░▓░▓▓▓░░▓▓▓░░░░▓░░▓▓▓░░
@@ -287,4 +312,26 @@ noop";
 ▓░░░░▓▓▓▓░▓▓▓░░▓░░░░▓▓▓░░▓░░░░▓▓▓▓░▓░░░░"
         );
     }
+
+    #[test]
+    fn streaming() {
+        assert_eq!(signal_strength_streaming(INPUT.as_bytes()).unwrap(), 13140);
+        assert_eq!(
+            render_streaming(INPUT.as_bytes()).unwrap(),
+            part2(&parse_input(INPUT).unwrap())
+        );
+        assert!(matches!(
+            signal_strength_streaming("noop\naddx\n".as_bytes()),
+            Err(StreamError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn register() {
+        let instructions = parse_input("noop\naddx 3\naddx -5").unwrap();
+        assert_eq!(
+            register_values(instructions).collect_vec(),
+            vec![1, 1, 1, 4, 4]
+        );
+    }
 }
@@ -1,97 +1,417 @@
-use std::{cmp::Ordering, str::FromStr};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    str::FromStr,
+};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 
 type Parsed = Vec<(PacketValue, PacketValue)>;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-enum PacketValue {
+// Equality follows the puzzle's ordering so that it agrees with `Ord`: an
+// integer equals the list holding just that integer, so `[1] == 1` and
+// `[[2]] == 2` even though they print differently. There is deliberately no
+// `Hash` impl; compare the `Display` output when the exact structure matters.
+#[derive(Debug, Eq, Clone)]
+pub enum PacketValue {
     List(Vec<PacketValue>),
     Int(u32),
 }
 
-impl PacketValue {
-    fn is_lower_than(&self, other: &PacketValue) -> bool {
-        self.compare(other) == Ordering::Less
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PacketError {
+    UnexpectedEnd,
+    UnexpectedChar { position: usize, found: char },
+    InvalidNumber { position: usize },
+}
 
-    fn compare(&self, other: &PacketValue) -> Ordering {
+impl Display for PacketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            PacketValue::Int(value) => match other {
-                PacketValue::Int(other_value) => value.cmp(other_value),
-                PacketValue::List(_) => {
-                    PacketValue::List(vec![PacketValue::Int(*value)]).compare(other)
-                }
-            },
-            PacketValue::List(values) => match other {
-                PacketValue::Int(other_value) => {
-                    self.compare(&PacketValue::List(vec![PacketValue::Int(*other_value)]))
+            PacketError::UnexpectedEnd => write!(f, "unexpected end of packet"),
+            PacketError::UnexpectedChar { position, found } => {
+                write!(f, "unexpected {found:?} at position {position}")
+            }
+            PacketError::InvalidNumber { position } => {
+                write!(f, "invalid number at position {position}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PacketError {}
+
+struct PacketParser<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PacketParser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input: input.as_bytes(),
+            position: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn unexpected(&self) -> PacketError {
+        match self.peek() {
+            None => PacketError::UnexpectedEnd,
+            Some(_) => {
+                let found = std::str::from_utf8(&self.input[self.position..])
+                    .ok()
+                    .and_then(|rest| rest.chars().next())
+                    .unwrap_or(char::REPLACEMENT_CHARACTER);
+                PacketError::UnexpectedChar {
+                    position: self.position,
+                    found,
                 }
-                PacketValue::List(other_values) => {
-                    for i in 0..values.len() {
-                        if i >= other_values.len() {
-                            return Ordering::Greater;
-                        }
+            }
+        }
+    }
 
-                        let ordering = values[i].compare(&other_values[i]);
-                        if ordering != Ordering::Equal {
-                            return ordering;
-                        }
-                    }
+    fn parse_packet(mut self) -> Result<PacketValue, PacketError> {
+        let packet = self.parse_value()?;
+        match self.peek() {
+            None => Ok(packet),
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<PacketValue, PacketError> {
+        match self.peek() {
+            Some(b'[') => self.parse_list(),
+            Some(b'0'..=b'9') => self.parse_int(),
+            _ => Err(self.unexpected()),
+        }
+    }
 
-                    return Ordering::Less;
+    fn parse_list(&mut self) -> Result<PacketValue, PacketError> {
+        self.position += 1;
+        let mut items = vec![];
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(PacketValue::List(items));
+        }
+
+        loop {
+            items.push(self.parse_value()?);
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(PacketValue::List(items));
                 }
-            },
+                _ => return Err(self.unexpected()),
+            }
         }
     }
+
+    fn parse_int(&mut self) -> Result<PacketValue, PacketError> {
+        let start = self.position;
+        let mut value = 0u32;
+        while let Some(digit @ b'0'..=b'9') = self.peek() {
+            value = value
+                .checked_mul(10)
+                .and_then(|value| value.checked_add((digit - b'0') as u32))
+                .ok_or(PacketError::InvalidNumber { position: start })?;
+            self.position += 1;
+        }
+        Ok(PacketValue::Int(value))
+    }
 }
 
 impl FromStr for PacketValue {
-    type Err = ();
+    type Err = PacketError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.starts_with('[') {
-            let mut items = Vec::new();
-            let mut current_item = Vec::new();
-            let mut bracket_level = 0;
-            let mut iter = s[1..s.len() - 1].chars();
-            while let Some(c) = iter.next() {
-                if c == '[' {
-                    bracket_level += 1;
-                } else if c == ']' {
-                    bracket_level -= 1;
-                }
+        PacketParser::new(s).parse_packet()
+    }
+}
+
+impl Display for PacketValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PacketValue::Int(value) => write!(f, "{value}"),
+            PacketValue::List(values) => write!(f, "[{}]", values.iter().join(",")),
+        }
+    }
+}
+
+impl Ord for PacketValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (PacketValue::Int(left), PacketValue::Int(right)) => left.cmp(right),
+            (PacketValue::List(left), PacketValue::List(right)) => left.cmp(right),
+            (PacketValue::Int(left), PacketValue::List(right)) => {
+                [PacketValue::Int(*left)].as_slice().cmp(right.as_slice())
+            }
+            (PacketValue::List(left), PacketValue::Int(right)) => {
+                left.as_slice().cmp([PacketValue::Int(*right)].as_slice())
+            }
+        }
+    }
+}
 
-                if bracket_level == 0 && c == ',' {
-                    items.push(current_item.iter().collect::<String>().parse().unwrap());
-                    current_item.clear();
-                    continue;
+impl PartialEq for PacketValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl PartialOrd for PacketValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    LeftSmaller { left: u32, right: u32 },
+    RightSmaller { left: u32, right: u32 },
+    LeftRanOut,
+    RightRanOut,
+}
+
+impl Decision {
+    fn ordering(&self) -> Ordering {
+        match self {
+            Decision::LeftSmaller { .. } | Decision::LeftRanOut => Ordering::Less,
+            Decision::RightSmaller { .. } | Decision::RightRanOut => Ordering::Greater,
+        }
+    }
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decision::LeftSmaller { .. } => {
+                write!(f, "Left side is smaller, so inputs are in the right order")
+            }
+            Decision::RightSmaller { .. } => {
+                write!(
+                    f,
+                    "Right side is smaller, so inputs are not in the right order"
+                )
+            }
+            Decision::LeftRanOut => write!(
+                f,
+                "Left side ran out of items, so inputs are in the right order"
+            ),
+            Decision::RightRanOut => write!(
+                f,
+                "Right side ran out of items, so inputs are not in the right order"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Compare {
+        depth: usize,
+        left: PacketValue,
+        right: PacketValue,
+    },
+    Promote {
+        depth: usize,
+        side: Side,
+        value: PacketValue,
+    },
+    Decide {
+        depth: usize,
+        decision: Decision,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub ordering: Ordering,
+    pub decision: Option<Decision>,
+    // List indices leading to the deciding pair. An index past the end of a
+    // list marks the side that ran out of items.
+    pub path: Vec<usize>,
+    pub steps: Vec<Step>,
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            match step {
+                Step::Compare { depth, left, right } => {
+                    writeln!(f, "{}- Compare {left} vs {right}", "  ".repeat(*depth))?
+                }
+                Step::Promote { depth, side, value } => writeln!(
+                    f,
+                    "{}- Mixed types; convert {} to {value} and retry comparison",
+                    "  ".repeat(*depth),
+                    if *side == Side::Left { "left" } else { "right" }
+                )?,
+                Step::Decide { depth, decision } => {
+                    writeln!(f, "{}- {decision}", "  ".repeat(*depth))?
                 }
+            }
+        }
+        Ok(())
+    }
+}
 
-                current_item.push(c);
+impl Explanation {
+    // Renders both packets next to each other and marks the deciding
+    // position in each of them.
+    pub fn render(&self, left: &PacketValue, right: &PacketValue) -> String {
+        let (left_text, right_text) = (left.to_string(), right.to_string());
+        let marker = |packet: &PacketValue| match self.decision {
+            Some(_) => {
+                let (start, end) = packet.locate(&self.path, 0);
+                format!("{}{}", " ".repeat(start), "^".repeat(end - start))
             }
+            None => String::new(),
+        };
+        let width = left_text.len();
 
-            if !current_item.is_empty() {
-                items.push(current_item.iter().collect::<String>().parse().unwrap());
+        format!(
+            "{left_text:<width$}   {right_text}\n{:<width$}   {}",
+            marker(left),
+            marker(right)
+        )
+        .trim_end()
+        .to_string()
+    }
+}
+
+impl PacketValue {
+    pub fn explain(&self, other: &PacketValue) -> Explanation {
+        let mut path = vec![];
+        let mut steps = vec![];
+        let decision = self.explain_into(other, 0, &mut path, &mut steps);
+
+        Explanation {
+            ordering: decision.map_or(Ordering::Equal, |decision| decision.ordering()),
+            decision,
+            path,
+            steps,
+        }
+    }
+
+    fn explain_into(
+        &self,
+        other: &PacketValue,
+        depth: usize,
+        path: &mut Vec<usize>,
+        steps: &mut Vec<Step>,
+    ) -> Option<Decision> {
+        steps.push(Step::Compare {
+            depth,
+            left: self.clone(),
+            right: other.clone(),
+        });
+
+        let decision = match (self, other) {
+            (PacketValue::Int(left), PacketValue::Int(right)) => match left.cmp(right) {
+                Ordering::Less => Some(Decision::LeftSmaller {
+                    left: *left,
+                    right: *right,
+                }),
+                Ordering::Greater => Some(Decision::RightSmaller {
+                    left: *left,
+                    right: *right,
+                }),
+                Ordering::Equal => None,
+            },
+            (PacketValue::List(left), PacketValue::List(right)) => {
+                for i in 0.. {
+                    path.push(i);
+                    match (left.get(i), right.get(i)) {
+                        (None, None) => break,
+                        (None, Some(_)) => return Self::decide(Decision::LeftRanOut, depth, steps),
+                        (Some(_), None) => {
+                            return Self::decide(Decision::RightRanOut, depth, steps)
+                        }
+                        (Some(l), Some(r)) => {
+                            if let Some(decision) = l.explain_into(r, depth + 1, path, steps) {
+                                return Some(decision);
+                            }
+                        }
+                    }
+                    path.pop();
+                }
+                path.pop();
+                return None;
             }
+            (PacketValue::Int(_), PacketValue::List(_)) => {
+                let value = PacketValue::List(vec![self.clone()]);
+                steps.push(Step::Promote {
+                    depth: depth + 1,
+                    side: Side::Left,
+                    value: value.clone(),
+                });
+                return value.explain_into(other, depth + 1, path, steps);
+            }
+            (PacketValue::List(_), PacketValue::Int(_)) => {
+                let value = PacketValue::List(vec![other.clone()]);
+                steps.push(Step::Promote {
+                    depth: depth + 1,
+                    side: Side::Right,
+                    value: value.clone(),
+                });
+                return self.explain_into(&value, depth + 1, path, steps);
+            }
+        };
+
+        decision.and_then(|decision| Self::decide(decision, depth, steps))
+    }
 
-            Ok(PacketValue::List(items))
-        } else {
-            Ok(PacketValue::Int(s.parse().unwrap()))
+    fn decide(decision: Decision, depth: usize, steps: &mut Vec<Step>) -> Option<Decision> {
+        steps.push(Step::Decide {
+            depth: depth + 1,
+            decision,
+        });
+        Some(decision)
+    }
+
+    // Character span of the element at `path` in the packet's `Display`
+    // output. Integers stand for their promoted list, and an index past the
+    // end of a list points at its closing bracket.
+    fn locate(&self, path: &[usize], offset: usize) -> (usize, usize) {
+        let length = self.to_string().len();
+        match (self, path.split_first()) {
+            (PacketValue::List(values), Some((index, rest))) => {
+                let mut start = offset + 1;
+                for value in values.iter().take(*index) {
+                    start += value.to_string().len() + 1;
+                }
+                match values.get(*index) {
+                    Some(value) => value.locate(rest, start),
+                    None => (offset + length - 1, offset + length),
+                }
+            }
+            _ => (offset, offset + length),
         }
     }
 }
 
 #[aoc_generator(day13)]
-fn parse_input(input: &str) -> Parsed {
+fn parse_input(input: &str) -> Result<Parsed, PacketError> {
     input
         .split("\n\n")
         .map(|pair| {
-            pair.lines()
-                .map(|line| line.parse().unwrap())
+            let (left, right) = pair
+                .lines()
                 .collect_tuple()
-                .unwrap()
+                .ok_or(PacketError::UnexpectedEnd)?;
+            Ok((left.parse()?, right.parse()?))
         })
         .collect()
 }
@@ -101,27 +421,41 @@ fn part1(input: &Parsed) -> usize {
     input
         .iter()
         .enumerate()
-        .filter(|(_, (left, right))| left.is_lower_than(right))
+        .filter(|(_, (left, right))| left < right)
         .map(|(i, _)| i + 1)
         .sum()
 }
 
+#[aoc(day13, part1, Explain)]
+fn part1_explain(input: &Parsed) -> usize {
+    for (i, (left, right)) in input.iter().enumerate() {
+        let explanation = left.explain(right);
+        println!("== Pair {} ==", i + 1);
+        print!("{explanation}");
+        println!("{}\n", explanation.render(left, right));
+    }
+
+    part1(input)
+}
+
+// Packets equal to a divider, such as `[2]` for `[[2]]`, sort before it like
+// in a stable sort with the dividers appended last, so the dividers are
+// found by their tag rather than with `==`.
 #[aoc(day13, part2)]
 fn part2(input: &Parsed) -> usize {
-    let divider_one = PacketValue::List(vec![PacketValue::List(vec![PacketValue::Int(2)])]);
-    let divider_two = PacketValue::List(vec![PacketValue::List(vec![PacketValue::Int(6)])]);
-    let mut input = input.clone();
-    input.append(&mut vec![(divider_one.clone(), divider_two.clone())]);
-    let ordered = input
+    let dividers: [PacketValue; 2] = ["[[2]]", "[[6]]"].map(|divider| divider.parse().unwrap());
+    let mut ordered = input
         .iter()
-        .flat_map(|group| [group.0.clone(), group.1.clone()])
-        .sorted_by(|a, b| a.compare(b))
+        .flat_map(|(left, right)| [(left, false), (right, false)])
+        .chain(dividers.iter().map(|divider| (divider, true)))
         .collect_vec();
+    ordered.sort_by_key(|(packet, _)| *packet);
 
-    let index_one = ordered.iter().position(|x| x == &divider_one).unwrap() + 1;
-    let index_two = ordered.iter().position(|x| x == &divider_two).unwrap() + 1;
-
-    index_one * index_two
+    ordered
+        .iter()
+        .positions(|(_, is_divider)| *is_divider)
+        .map(|index| index + 1)
+        .product()
 }
 
 #[cfg(test)]
@@ -154,13 +488,111 @@ mod tests {
 [1,[2,[3,[4,[5,6,0]]]],8,9]"
     }
 
+    #[test]
+    fn round_trip() {
+        for line in input().lines().filter(|line| !line.is_empty()) {
+            assert_eq!(line.parse::<PacketValue>().unwrap().to_string(), line);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            "[1,2".parse::<PacketValue>(),
+            Err(PacketError::UnexpectedEnd)
+        );
+        assert_eq!(
+            "[1,,2]".parse::<PacketValue>(),
+            Err(PacketError::UnexpectedChar {
+                position: 3,
+                found: ','
+            })
+        );
+        assert_eq!(
+            "[[1]]]".parse::<PacketValue>(),
+            Err(PacketError::UnexpectedChar {
+                position: 5,
+                found: ']'
+            })
+        );
+        assert_eq!(
+            "[99999999999]".parse::<PacketValue>(),
+            Err(PacketError::InvalidNumber { position: 1 })
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        let packet = |s: &str| s.parse::<PacketValue>().unwrap();
+        assert!(packet("[1,1,3,1,1]") < packet("[1,1,5,1,1]"));
+        assert!(packet("[[1],[2,3,4]]") < packet("[[1],4]"));
+        assert!(packet("[9]") > packet("[[8,7,6]]"));
+        assert_eq!(packet("[[[]]]").cmp(&packet("[[[]]]")), Ordering::Equal);
+        assert_eq!(packet("[1]").cmp(&packet("1")), Ordering::Equal);
+        assert_eq!(packet("[[2]]"), packet("2"));
+        assert_ne!(packet("[[2]]").to_string(), packet("2").to_string());
+    }
+
+    #[test]
+    fn explain_matches_ordering() {
+        for (left, right) in parse_input(input()).unwrap() {
+            assert_eq!(left.explain(&right).ordering, left.cmp(&right));
+            assert_eq!(right.explain(&left).ordering, right.cmp(&left));
+        }
+    }
+
+    #[test]
+    fn explain_steps() {
+        let packets = parse_input(input()).unwrap();
+
+        let (left, right) = &packets[2];
+        let explanation = left.explain(right);
+        assert_eq!(
+            explanation.to_string(),
+            "- Compare [9] vs [[8,7,6]]
+  - Compare 9 vs [8,7,6]
+    - Mixed types; convert left to [9] and retry comparison
+    - Compare [9] vs [8,7,6]
+      - Compare 9 vs 8
+        - Right side is smaller, so inputs are not in the right order
+"
+        );
+        assert_eq!(
+            explanation.decision,
+            Some(Decision::RightSmaller { left: 9, right: 8 })
+        );
+        assert_eq!(explanation.path, vec![0, 0]);
+        assert_eq!(
+            explanation.render(left, right),
+            "[9]   [[8,7,6]]
+ ^      ^"
+        );
+
+        let (left, right) = &packets[3];
+        let explanation = left.explain(right);
+        assert_eq!(explanation.decision, Some(Decision::LeftRanOut));
+        assert_eq!(explanation.path, vec![3]);
+        assert_eq!(
+            explanation.render(left, right),
+            "[[4,4],4,4]   [[4,4],4,4,4]
+          ^              ^"
+        );
+    }
+
     #[test]
     fn sample1() {
-        assert_eq!(part1(&parse_input(input())), 13);
+        assert_eq!(part1(&parse_input(input()).unwrap()), 13);
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&parse_input(input())), 140);
+        assert_eq!(part2(&parse_input(input()).unwrap()), 140);
+    }
+
+    #[test]
+    fn packets_equal_to_dividers() {
+        // [2] == [[2]] under the puzzle ordering but sorts before the divider
+        assert_eq!(part2(&parse_input("[2]\n[3]").unwrap()), 8);
+        assert_eq!(part2(&parse_input("[[6]]\n[2]").unwrap()), 2 * 4);
     }
 }
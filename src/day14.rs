@@ -1,244 +1,365 @@
-use std::collections::HashMap;
+use std::fmt::{self, Display};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 
 type Parsed = Vec<Line>;
 
-type Point = (usize, usize);
+pub type Point = (i64, i64);
 
-#[derive(Debug, Copy, Clone)]
-struct Line {
+// Axis-aligned rock segment with `from <= to`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Line {
     from: Point,
     to: Point,
 }
 
-#[derive(Debug, Copy, Clone)]
+impl Line {
+    fn new(a: Point, b: Point) -> Option<Line> {
+        (a.0 == b.0 || a.1 == b.1).then(|| Line {
+            from: a.min(b),
+            to: a.max(b),
+        })
+    }
+
+    fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        (self.from.1..=self.to.1).flat_map(move |y| (self.from.0..=self.to.0).map(move |x| (x, y)))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Tile {
     Air,
     Rock,
     Sand,
 }
 
-#[aoc_generator(day14)]
-fn parse_input(input: &str) -> Parsed {
-    input
-        .lines()
-        .flat_map(|line| {
-            line.split(" -> ")
-                .map(|point| {
-                    point
-                        .split(',')
-                        .map(|part| part.parse().unwrap())
-                        .collect_tuple()
-                        .unwrap()
-                })
-                .tuple_windows()
-                .map(|(from, to)| Line { from, to })
-        })
-        .collect()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    InvalidPoint { line: usize, point: String },
+    Diagonal { line: usize, from: Point, to: Point },
 }
 
-fn bounds_x(lines: &Parsed) -> (usize, usize) {
-    let min_x = lines.iter().min_by_key(|line| line.to.0).unwrap();
-    let max_x = lines.iter().max_by_key(|line| line.from.0).unwrap();
-    (min_x.to.0, max_x.from.0)
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidPoint { line, point } => {
+                write!(f, "invalid point {point:?} on line {line}")
+            }
+            ParseError::Diagonal { line, from, to } => {
+                write!(f, "diagonal segment {from:?} -> {to:?} on line {line}")
+            }
+        }
+    }
 }
 
-fn bounds_y(lines: &Parsed) -> (usize, usize) {
-    let min_y = lines.iter().min_by_key(|line| line.from.1).unwrap();
-    let max_y = lines.iter().max_by_key(|line| line.to.1).unwrap();
-    (min_y.from.1, max_y.to.1)
+impl std::error::Error for ParseError {}
+
+fn parse_point(point: &str, line: usize) -> Result<Point, ParseError> {
+    point
+        .split_once(',')
+        .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+        .ok_or_else(|| ParseError::InvalidPoint {
+            line,
+            point: point.to_string(),
+        })
 }
 
-fn build_tiles(
-    lines: &Parsed,
-    bounds_x: (usize, usize),
-    bounds_y: (usize, usize),
-) -> Vec<Vec<Tile>> {
-    let width = bounds_x.1 - bounds_x.0 + 1;
-    let height = bounds_y.1 + 1;
-    let mut grid = vec![vec![Tile::Air; width]; height];
-
-    lines.iter().for_each(|line| {
-        if line.from.0 < line.to.0 {
-            let y = line.from.1;
-            for x in line.from.0..=line.to.0 {
-                grid[y][x - bounds_x.0] = Tile::Rock
-            }
-        } else if line.from.0 > line.to.0 {
-            let y = line.from.1;
-            for x in line.to.0..=line.from.0 {
-                grid[y][x - bounds_x.0] = Tile::Rock
-            }
-        } else if line.from.1 < line.to.1 {
-            let x = line.from.0;
-            for y in line.from.1..=line.to.1 {
-                grid[y][x - bounds_x.0] = Tile::Rock
-            }
-        } else if line.to.1 < line.from.1 {
-            let x = line.from.0;
-            for y in line.to.1..=line.from.1 {
-                grid[y][x - bounds_x.0] = Tile::Rock
-            }
+#[aoc_generator(day14)]
+fn parse_input(input: &str) -> Result<Parsed, ParseError> {
+    let mut lines = vec![];
+    for (i, path) in input.lines().enumerate() {
+        let points = path
+            .split(" -> ")
+            .map(|point| parse_point(point, i + 1))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (from, to) in points.into_iter().tuple_windows() {
+            lines.push(Line::new(from, to).ok_or(ParseError::Diagonal {
+                line: i + 1,
+                from,
+                to,
+            })?);
         }
-    });
+    }
+    Ok(lines)
+}
 
-    grid
+fn bounds_x(lines: &Parsed) -> Option<(i64, i64)> {
+    lines
+        .iter()
+        .flat_map(|line| [line.from.0, line.to.0])
+        .minmax()
+        .into_option()
 }
 
-fn print_grid(grid: &Vec<Vec<Tile>>) {
-    grid.iter().for_each(|row| {
-        row.iter().for_each(|cell| match cell {
-            Tile::Air => print!(" "),
-            Tile::Rock => print!("#"),
-            Tile::Sand => print!("O"),
-        });
-        println!("");
-    });
+fn bounds_y(lines: &Parsed) -> Option<(i64, i64)> {
+    lines
+        .iter()
+        .flat_map(|line| [line.from.1, line.to.1])
+        .minmax()
+        .into_option()
 }
 
-fn find_next_sand_spot(grid: &Vec<Vec<Tile>>, x: usize) -> Option<(usize, usize)> {
-    let mut x = x as i32;
-    let mut y = 0 as i32;
-    let width = grid[0].len() as i32;
-    let height = grid.len() as i32;
-    while x >= 0 && x < width && y < height {
-        if y + 1 >= height || matches!(grid[y as usize + 1][x as usize], Tile::Air) {
-            y += 1;
-            continue;
-        }
-        if x - 1 < 0 || matches!(grid[y as usize + 1][x as usize - 1], Tile::Air) {
-            x -= 1;
-            y += 1;
-            continue;
-        }
-        if x + 1 >= width || matches!(grid[y as usize + 1][x as usize + 1], Tile::Air) {
-            x += 1;
-            y += 1;
-            continue;
+pub const SOURCE: Point = (500, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaveError {
+    // neither rocks nor sources to size the grid by
+    Empty,
+    // `Floor::BelowRocks` needs at least one rock
+    NoRocks,
+}
+
+impl Display for CaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaveError::Empty => write!(f, "cave has neither rocks nor sources"),
+            CaveError::NoRocks => write!(f, "floor below the rocks needs at least one rock"),
         }
-        return Some((x as usize, y as usize));
     }
+}
 
-    None
+impl std::error::Error for CaveError {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Floor {
+    Absent,
+    At(i64),
+    BelowRocks(i64),
 }
 
-fn build_tiles_b(lines: &Parsed) -> HashMap<Point, Tile> {
-    let mut grid = HashMap::new();
+#[derive(Debug, Clone)]
+struct Source {
+    position: Point,
+    path: Vec<Point>,
+    grains: usize,
+    exhausted: bool,
+}
 
-    lines.iter().for_each(|line| {
-        if line.from.0 < line.to.0 {
-            let y = line.from.1;
-            for x in line.from.0..=line.to.0 {
-                grid.insert((x, y), Tile::Rock);
-            }
-        } else if line.from.0 > line.to.0 {
-            let y = line.from.1;
-            for x in line.to.0..=line.from.0 {
-                grid.insert((x, y), Tile::Rock);
-            }
-        } else if line.from.1 < line.to.1 {
-            let x = line.from.0;
-            for y in line.from.1..=line.to.1 {
-                grid.insert((x, y), Tile::Rock);
-            }
-        } else if line.to.1 < line.from.1 {
-            let x = line.from.0;
-            for y in line.to.1..=line.from.1 {
-                grid.insert((x, y), Tile::Rock);
+// Dense cave grid shared by both parts. Sand that leaves the grid without a
+// floor falls into the abyss. Every source keeps the fall path of its
+// previous grain, so each new grain resumes from the last position that is
+// still free. With a floor the grid reaches `floor` columns past every rock
+// and source on both sides, as far as sand can spread, even below x = 0.
+pub struct Cave {
+    tiles: Vec<Tile>,
+    min_x: i64,
+    width: usize,
+    height: i64,
+    floor: Option<i64>,
+    sources: Vec<Source>,
+}
+
+impl Cave {
+    pub fn new(lines: &Parsed, sources: &[Point], floor: Floor) -> Result<Self, CaveError> {
+        let rocks_y = bounds_y(lines);
+        let (mut min_x, mut max_x) = bounds_x(lines)
+            .into_iter()
+            .flat_map(|(min, max)| [min, max])
+            .chain(sources.iter().map(|source| source.0))
+            .minmax()
+            .into_option()
+            .ok_or(CaveError::Empty)?;
+        let max_y = rocks_y
+            .map(|(_, max)| max)
+            .into_iter()
+            .chain(sources.iter().map(|source| source.1))
+            .max()
+            .ok_or(CaveError::Empty)?;
+
+        let floor = match floor {
+            Floor::Absent => None,
+            Floor::At(y) => Some(y),
+            Floor::BelowRocks(offset) => Some(rocks_y.ok_or(CaveError::NoRocks)?.1 + offset),
+        };
+        let height = match floor {
+            Some(floor) => {
+                min_x -= floor;
+                max_x += floor;
+                floor
             }
+            None => max_y + 1,
+        };
+        let min_x = min_x - 1;
+        let width = (max_x + 2 - min_x) as usize;
+
+        let mut cave = Cave {
+            tiles: vec![Tile::Air; width * height.max(0) as usize],
+            min_x,
+            width,
+            height,
+            floor,
+            sources: sources
+                .iter()
+                .map(|position| Source {
+                    position: *position,
+                    path: vec![],
+                    grains: 0,
+                    exhausted: false,
+                })
+                .collect(),
+        };
+
+        for point in lines.iter().flat_map(|line| line.points()) {
+            cave.set(point, Tile::Rock);
         }
-    });
 
-    grid
-}
+        Ok(cave)
+    }
 
-fn print_grid_b(grid: &HashMap<Point, Tile>) {
-    let min_x = grid.keys().min_by_key(|key| key.0).unwrap().0;
-    let max_x = grid.keys().max_by_key(|key| key.0).unwrap().0;
-    let max_y = grid.keys().max_by_key(|key| key.1).unwrap().1;
-
-    for y in 0..=max_y {
-        for x in min_x..=max_x {
-            if let Some(tile) = grid.get(&(x, y)) {
-                match tile {
-                    Tile::Air => print!(" "),
-                    Tile::Rock => print!("#"),
-                    Tile::Sand => print!("O"),
-                }
-            } else {
-                print!(" ");
+    fn index(&self, (x, y): Point) -> Option<usize> {
+        let column = usize::try_from(x - self.min_x).ok()?;
+        (column < self.width && (0..self.height).contains(&y))
+            .then(|| y as usize * self.width + column)
+    }
+
+    fn get(&self, point: Point) -> Option<Tile> {
+        self.index(point).map(|index| self.tiles[index])
+    }
+
+    fn set(&mut self, point: Point, tile: Tile) {
+        if let Some(index) = self.index(point) {
+            self.tiles[index] = tile;
+        }
+    }
+
+    // Next position of a grain at `(x, y)`: `Some(None)` if it comes to rest,
+    // `None` if it falls out of the cave.
+    fn next_position(&self, (x, y): Point) -> Option<Option<Point>> {
+        if self.floor == Some(y + 1) {
+            return Some(None);
+        }
+        for next in [(x, y + 1), (x - 1, y + 1), (x + 1, y + 1)] {
+            match self.get(next) {
+                Some(Tile::Air) => return Some(Some(next)),
+                Some(_) => {}
+                None => return None,
             }
         }
-        println!("");
+        Some(None)
     }
-    println!("");
-}
 
-fn find_next_sand_spot_b(grid: &HashMap<Point, Tile>, x: usize, height: usize) -> (usize, usize) {
-    let mut x = x;
-    let mut y = 0;
-    loop {
-        if !grid.contains_key(&(x, y + 1)) && y + 1 < height {
-            y += 1;
-            continue;
+    pub fn drop_grain(&mut self, source: usize) -> Option<Point> {
+        if self.sources[source].exhausted {
+            return None;
+        }
+        let mut path = std::mem::take(&mut self.sources[source].path);
+        if path.is_empty() {
+            let position = self.sources[source].position;
+            if self.get(position) != Some(Tile::Air) {
+                self.sources[source].exhausted = true;
+                return None;
+            }
+            path.push(position);
         }
-        if !grid.contains_key(&(x - 1, y + 1)) && y + 1 < height {
-            x -= 1;
-            y += 1;
-            continue;
+
+        let rest = loop {
+            let position = *path.last().unwrap();
+            match self.next_position(position) {
+                Some(Some(next)) => path.push(next),
+                Some(None) => {
+                    path.pop();
+                    break Some(position);
+                }
+                None => break None,
+            }
+        };
+
+        let Some(position) = rest else {
+            self.sources[source].exhausted = true;
+            return None;
+        };
+        self.set(position, Tile::Sand);
+        self.sources[source].path = path;
+        self.sources[source].grains += 1;
+
+        // Grains from other sources may have been resting on this one's path.
+        for other in &mut self.sources {
+            if let Some(blocked) = other.path.iter().position(|p| *p == position) {
+                other.path.truncate(blocked);
+            }
         }
-        if !grid.contains_key(&(x + 1, y + 1)) && y + 1 < height {
-            x += 1;
-            y += 1;
-            continue;
+
+        Some(position)
+    }
+
+    // Drops one grain per source in turn until no source can produce any
+    // more, returning the number of grains that came to rest per source.
+    pub fn run(&mut self) -> Vec<usize> {
+        while self.sources.iter().any(|source| !source.exhausted) {
+            for source in 0..self.sources.len() {
+                self.drop_grain(source);
+            }
         }
-        return (x as usize, y as usize);
+        self.sources.iter().map(|source| source.grains).collect()
     }
-}
 
-#[aoc(day14, part1)]
-fn part1(input: &Parsed) -> usize {
-    let bounds_x = bounds_x(input);
-    let bounds_y = bounds_y(input);
-    let mut grid = build_tiles(input, bounds_x, bounds_y);
+    pub fn fill(&mut self) -> usize {
+        self.run().iter().sum()
+    }
 
-    print_grid(&grid);
+    // With a floor, sand ends up in exactly the cells reachable from the
+    // sources, so they can be counted row by row without simulating grains.
+    pub fn flood_fill(&self) -> Option<usize> {
+        self.floor?;
+
+        let mut reachable = vec![false; self.width];
+        let mut count = 0;
+        for y in 0..self.height {
+            let previous = reachable;
+            reachable = (0..self.width)
+                .map(|i| {
+                    let x = self.min_x + i as i64;
+                    let from_above = y > 0
+                        && previous[i.saturating_sub(1)..=(i + 1).min(self.width - 1)]
+                            .iter()
+                            .any(|r| *r);
+                    let is_source = self.sources.iter().any(|s| s.position == (x, y));
+                    (from_above || is_source) && self.get((x, y)) != Some(Tile::Rock)
+                })
+                .collect();
+            count += reachable.iter().filter(|r| **r).count();
+        }
 
-    let mut count = 0;
-    while let Some(pos) = find_next_sand_spot(&grid, 500 - bounds_x.0) {
-        grid[pos.1][pos.0] = Tile::Sand;
-        count += 1;
+        Some(count)
     }
+}
 
-    println!("");
-    print_grid(&grid);
+impl Display for Cave {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.tiles.chunks(self.width) {
+            let row: String = row
+                .iter()
+                .map(|tile| match tile {
+                    Tile::Air => '.',
+                    Tile::Rock => '#',
+                    Tile::Sand => 'o',
+                })
+                .collect();
+            writeln!(f, "{row}")?;
+        }
+        if self.floor.is_some() {
+            writeln!(f, "{}", "#".repeat(self.width))?;
+        }
+        Ok(())
+    }
+}
 
-    count
+#[aoc(day14, part1)]
+fn part1(input: &Parsed) -> Result<usize, CaveError> {
+    Ok(Cave::new(input, &[SOURCE], Floor::Absent)?.fill())
 }
 
 #[aoc(day14, part2)]
-fn part2(input: &Parsed) -> usize {
-    let bounds_y = bounds_y(input);
-    let mut grid = build_tiles_b(input);
-
-    let height = bounds_y.1 + 2;
-
-    print_grid_b(&grid);
-
-    let mut count = 0;
-    loop {
-        let pos = find_next_sand_spot_b(&grid, 500, height);
-        if pos == (500, 0) {
-            println!("");
-            print_grid_b(&grid);
-            return count + 1;
-        }
+fn part2(input: &Parsed) -> Result<usize, CaveError> {
+    Ok(Cave::new(input, &[SOURCE], Floor::BelowRocks(2))?.fill())
+}
 
-        grid.insert(pos, Tile::Sand);
-        count += 1;
-    }
+#[aoc(day14, part2, FloodFill)]
+fn part2_flood_fill(input: &Parsed) -> Result<usize, CaveError> {
+    Ok(Cave::new(input, &[SOURCE], Floor::BelowRocks(2))?
+        .flood_fill()
+        .unwrap())
 }
 
 #[cfg(test)]
@@ -252,11 +373,161 @@ mod tests {
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&parse_input(input())), 24);
+        assert_eq!(part1(&parse_input(input()).unwrap()), Ok(24));
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&parse_input(input())), 93);
+        assert_eq!(part2(&parse_input(input()).unwrap()), Ok(93));
+        assert_eq!(part2_flood_fill(&parse_input(input()).unwrap()), Ok(93));
+    }
+
+    #[test]
+    fn reversed_segments() {
+        let reversed = parse_input(
+            "496,6 -> 498,6 -> 498,4
+494,9 -> 502,9 -> 502,4 -> 503,4",
+        )
+        .unwrap();
+        let lines = parse_input(input()).unwrap();
+        assert_eq!(bounds_x(&reversed), Some((494, 503)));
+        assert_eq!(bounds_y(&reversed), Some((4, 9)));
+        assert_eq!(bounds_x(&lines), bounds_x(&reversed));
+        assert_eq!(bounds_y(&lines), bounds_y(&reversed));
+        assert_eq!(Line::new((500, 4), (498, 4)), Line::new((498, 4), (500, 4)));
+        assert_eq!(part1(&reversed), Ok(24));
+        assert_eq!(part2(&reversed), Ok(93));
+    }
+
+    #[test]
+    fn single_reversed_segment_bounds() {
+        let lines = parse_input("510,3 -> 505,3\n500,1 -> 500,7").unwrap();
+        assert_eq!(bounds_x(&lines), Some((500, 510)));
+        assert_eq!(bounds_y(&lines), Some((1, 7)));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse_input("498,4 -> 498,6\n498,6 -> 496,8"),
+            Err(ParseError::Diagonal {
+                line: 2,
+                from: (498, 6),
+                to: (496, 8)
+            })
+        );
+        assert_eq!(
+            parse_input("498,4 -> 498"),
+            Err(ParseError::InvalidPoint {
+                line: 1,
+                point: "498".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn floor_beyond_zero() {
+        // The pile under (2, 0) spreads to x = -3 on the row above the floor.
+        let rock = parse_input("10,5 -> 11,5").unwrap();
+        let mut cave = Cave::new(&rock, &[(2, 0)], Floor::At(6)).unwrap();
+        assert_eq!(cave.flood_fill(), Some(36));
+        assert_eq!(cave.fill(), 36);
+        assert_eq!(cave.get((-3, 5)), Some(Tile::Sand));
+        assert_eq!(cave.get((-4, 5)), Some(Tile::Air));
+    }
+
+    #[test]
+    fn without_rocks() {
+        let mut cave = Cave::new(&vec![], &[(500, 0), (504, 0)], Floor::At(3)).unwrap();
+        // two pyramids of 9 that share the tile (502, 2)
+        assert_eq!(cave.flood_fill(), Some(17));
+        assert_eq!(cave.run(), vec![8, 9]);
+        assert_eq!(
+            Cave::new(&vec![], &[SOURCE], Floor::Absent).unwrap().fill(),
+            0
+        );
+        assert_eq!(
+            Cave::new(&vec![], &[SOURCE], Floor::BelowRocks(2)).err(),
+            Some(CaveError::NoRocks)
+        );
+        assert_eq!(
+            Cave::new(&vec![], &[], Floor::At(3)).err(),
+            Some(CaveError::Empty)
+        );
+    }
+
+    #[test]
+    fn multiple_sources() {
+        // Two sources over a cup three wide and two deep, taking turns. The
+        // right source is blocked once its own grain rests at (3, 0), the
+        // left one fills the rest of the cup and then its own position.
+        let cup = parse_input("0,1 -> 0,3 -> 4,3 -> 4,1").unwrap();
+        let mut cave = Cave::new(&cup, &[(2, 0), (3, 0)], Floor::Absent).unwrap();
+        assert_eq!(cave.drop_grain(0), Some((2, 2)));
+        assert_eq!(cave.drop_grain(1), Some((3, 2)));
+        assert_eq!(cave.run(), vec![5, 3]);
+        for point in [(1, 1), (2, 1), (3, 1), (1, 2), (2, 0), (3, 0)] {
+            assert_eq!(cave.get(point), Some(Tile::Sand));
+        }
+        assert_eq!(cave.get((1, 0)), Some(Tile::Air));
+
+        let lines = parse_input(input()).unwrap();
+
+        let mut cave = Cave::new(&lines, &[SOURCE, (503, 0)], Floor::At(11)).unwrap();
+        let total = cave.fill();
+        assert_eq!(cave.flood_fill(), Some(total));
+        assert_eq!(
+            total,
+            cave.tiles
+                .iter()
+                .filter(|tile| **tile == Tile::Sand)
+                .count()
+        );
+
+        let mut cave = Cave::new(&lines, &[SOURCE, SOURCE], Floor::BelowRocks(2)).unwrap();
+        assert_eq!(cave.run(), vec![47, 46]);
+    }
+
+    #[test]
+    fn custom_floor() {
+        let lines = parse_input(input()).unwrap();
+        assert_eq!(
+            Cave::new(&lines, &[SOURCE], Floor::At(11)).unwrap().fill(),
+            93
+        );
+        assert_eq!(
+            Cave::new(&lines, &[SOURCE], Floor::At(3)).unwrap().fill(),
+            9
+        );
+        assert_eq!(
+            Cave::new(&lines, &[SOURCE], Floor::At(20)).unwrap().fill(),
+            Cave::new(&lines, &[SOURCE], Floor::At(20))
+                .unwrap()
+                .flood_fill()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn sample_render() {
+        let mut cave = Cave::new(&parse_input(input()).unwrap(), &[SOURCE], Floor::Absent).unwrap();
+        assert_eq!(cave.drop_grain(0), Some((500, 8)));
+        assert_eq!(cave.drop_grain(0), Some((499, 8)));
+        cave.fill();
+        assert_eq!(
+            cave.to_string(),
+            "............
+............
+.......o....
+......ooo...
+.....#ooo##.
+....o#ooo#..
+...###ooo#..
+.....oooo#..
+..o.ooooo#..
+.#########..
+"
+        );
+        assert_eq!(cave.flood_fill(), None);
     }
 }
@@ -4,37 +4,54 @@ use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 use regex::Regex;
 
+use crate::interval::{Interval, IntervalSet};
+
 type Parsed = Vec<Sensor>;
 
 static mut PART1_COORD_Y: i64 = 2000000;
 static mut PART2_MAX_COORD_XY: i64 = 4000000;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-struct Sensor {
-    sensor_location: Point,
-    beacon_location: Point,
-    range: i64,
+pub struct Sensor {
+    pub sensor_location: Point,
+    pub beacon_location: Point,
+    pub range: i64,
 }
 
 impl Sensor {
-    fn coverage_at_y(&self, y: i64) -> Option<Range<i64>> {
-        let relative_y = (y - self.sensor_location.y).abs();
-        let half_width = self.range - relative_y;
-        match half_width {
-            _ if half_width < 0 => None,
-            _ => Some(self.sensor_location.x - half_width..self.sensor_location.x + half_width + 1),
-        }
+    fn coverage_at_y(&self, y: i64) -> Option<Interval<i64>> {
+        let half_width = self.range - (y - self.sensor_location.y).abs();
+        (half_width >= 0).then(|| {
+            Interval::inclusive(
+                self.sensor_location.x - half_width,
+                self.sensor_location.x + half_width,
+            )
+        })
+    }
+
+    fn coverage_at_x(&self, x: i64) -> Option<Interval<i64>> {
+        let half_height = self.range - (x - self.sensor_location.x).abs();
+        (half_height >= 0).then(|| {
+            Interval::inclusive(
+                self.sensor_location.y - half_height,
+                self.sensor_location.y + half_height,
+            )
+        })
+    }
+
+    pub fn covers(&self, point: &Point) -> bool {
+        self.sensor_location.manhattan_distance(point) <= self.range as u64
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-struct Point {
-    x: i64,
-    y: i64,
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
 }
 
 impl Point {
-    fn manhattan_distance(&self, other: &Point) -> u64 {
+    pub fn manhattan_distance(&self, other: &Point) -> u64 {
         self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
     }
 }
@@ -63,14 +80,66 @@ fn parse_input(input: &str) -> Parsed {
         .collect()
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SensorField {
+    sensors: Vec<Sensor>,
+}
+
+impl From<Vec<Sensor>> for SensorField {
+    fn from(sensors: Vec<Sensor>) -> Self {
+        Self { sensors }
+    }
+}
+
+impl SensorField {
+    pub fn sensors(&self) -> &[Sensor] {
+        &self.sensors
+    }
+
+    pub fn row_coverage(&self, y: i64) -> IntervalSet<i64> {
+        self.sensors
+            .iter()
+            .filter_map(|s| s.coverage_at_y(y))
+            .collect()
+    }
+
+    pub fn column_coverage(&self, x: i64) -> IntervalSet<i64> {
+        self.sensors
+            .iter()
+            .filter_map(|s| s.coverage_at_x(x))
+            .collect()
+    }
+
+    pub fn covering_sensor(&self, point: &Point) -> Option<&Sensor> {
+        self.sensors.iter().find(|sensor| sensor.covers(point))
+    }
+
+    pub fn is_covered(&self, point: &Point) -> bool {
+        self.covering_sensor(point).is_some()
+    }
+
+    pub fn covered_area(&self, xs: Range<i64>, ys: Range<i64>) -> i64 {
+        let xs = IntervalSet::from(Interval::from(xs));
+        ys.map(|y| self.row_coverage(y).intersection(&xs).len())
+            .sum()
+    }
+
+    pub fn uncovered_points(&self, xs: Range<i64>, ys: Range<i64>) -> Vec<Point> {
+        let xs = IntervalSet::from(Interval::from(xs));
+        ys.flat_map(|y| {
+            xs.difference(&self.row_coverage(y))
+                .iter()
+                .flat_map(|gap| gap.to_range().map(move |x| Point { x, y }))
+                .collect_vec()
+        })
+        .collect()
+    }
+}
+
 #[aoc(day15, part1)]
 fn part1(input: &Parsed) -> i64 {
-    let coverages = input
-        .iter()
-        .filter_map(|sensor| sensor.coverage_at_y(unsafe { PART1_COORD_Y }))
-        .collect_vec();
-
-    let (covered_area, _) = covered_area(coverages);
+    let field = SensorField::from(input.clone());
+    let covered_area = field.row_coverage(unsafe { PART1_COORD_Y }).len();
     let beacons_in_area = input
         .iter()
         .filter(|sensor| sensor.beacon_location.y == unsafe { PART1_COORD_Y })
@@ -81,75 +150,117 @@ fn part1(input: &Parsed) -> i64 {
     covered_area - beacons_in_area
 }
 
-#[aoc(day15, part2)]
-fn part2(input: &Parsed) -> i64 {
-    let scan_range = 0..(unsafe { PART2_MAX_COORD_XY } + 1);
+fn tuning_frequency(beacon: Point) -> i64 {
+    beacon.x * 4000000 + beacon.y
+}
 
-    let beacon_pos = scan_range
-        .clone()
-        .rev()
-        .map(|y| {
-            let coverages = input
-                .iter()
-                .filter_map(|x| x.coverage_at_y(y))
-                .collect_vec();
-            let (_, parts) = covered_area(coverages);
+fn find_beacon_row_scan(input: &Parsed, max_coord: i64) -> Option<Point> {
+    let field = SensorField::from(input.clone());
+    let scan_range = IntervalSet::from(Interval::inclusive(0, max_coord));
 
-            parts
-                .iter()
-                .filter(|p| p.start <= scan_range.end && p.end >= scan_range.start)
-                .sorted_by(|a, b| a.start.cmp(&b.start))
-                .tuple_windows()
-                .find(|(a, b)| a.end != b.start)
-                .map(|(a, _)| Point { x: a.end, y })
-        })
-        .find_map(|p| p)
-        .unwrap();
-    let tuning_frequency = beacon_pos.x * 4000000 + beacon_pos.y;
-    tuning_frequency
+    (0..=max_coord).rev().find_map(|y| {
+        scan_range
+            .difference(&field.row_coverage(y))
+            .iter()
+            .next()
+            .map(|gap| Point { x: gap.start, y })
+    })
 }
 
-fn covered_area(mut ranges: Vec<Range<i64>>) -> (i64, Vec<Range<i64>>) {
-    let mut covered_parts = Vec::<Range<i64>>::new();
-    'next_range: while let Some(mut range) = ranges.pop() {
-        for covered in &covered_parts {
-            let overlap = range.start.max(covered.start)..range.end.min(covered.end);
-            if overlap.start >= overlap.end {
-                continue;
-            }
+// In rotated coordinates (u = x + y, v = x - y) every sensor covers an
+// axis-aligned square, so an uncovered point next to the covered area lies on
+// a line just outside one of those squares. Intersecting these lines with
+// each other and the search area's edges yields a small set of candidates.
+// A beacon in a one-wide diagonal gap can be two steps away from a sensor's
+// square, so the lattice points around odd intersections are tried as well.
+fn find_beacon_geometric(input: &Parsed, max_coord: i64) -> Option<Point> {
+    let mut u_lines = vec![0, max_coord, 2 * max_coord];
+    let mut v_lines = vec![-max_coord, 0, max_coord];
+    for sensor in input {
+        let Point { x, y } = sensor.sensor_location;
+        let reach = sensor.range + 1;
+        u_lines.extend([x + y - reach, x + y + reach]);
+        v_lines.extend([x - y - reach, x - y + reach]);
+    }
 
-            match overlap {
-                _ if covered.start <= range.start && covered.end >= range.end => {
-                    // full range already covered
-                    continue 'next_range;
-                }
-                _ if range.start <= covered.start && range.end >= covered.end => {
-                    // cut away middle
-                    ranges.push(range.start..covered.start);
-                    ranges.push(covered.end..range.end);
-                    continue 'next_range;
-                }
-                _ if covered.start <= range.start => range = covered.end..range.end, // cut away start
-                _ if covered.end >= range.end => range = range.start..covered.start, // cut away end
-                _ => (),
-            };
-        }
+    let edges = [0, max_coord];
+    let from_lines = u_lines
+        .iter()
+        .cartesian_product(&v_lines)
+        .flat_map(|(u, v)| {
+            let (x, y) = ((u + v).div_euclid(2), (u - v).div_euclid(2));
+            let odd = (u - v).rem_euclid(2);
+            (0..=odd)
+                .cartesian_product(0..=odd)
+                .map(move |(dx, dy)| Point {
+                    x: x + dx,
+                    y: y + dy,
+                })
+        });
+    let on_edges = edges.iter().flat_map(|edge| {
+        u_lines
+            .iter()
+            .flat_map(move |u| {
+                [
+                    Point {
+                        x: *edge,
+                        y: u - edge,
+                    },
+                    Point {
+                        x: u - edge,
+                        y: *edge,
+                    },
+                ]
+            })
+            .chain(v_lines.iter().flat_map(move |v| {
+                [
+                    Point {
+                        x: *edge,
+                        y: edge - v,
+                    },
+                    Point {
+                        x: v + edge,
+                        y: *edge,
+                    },
+                ]
+            }))
+    });
 
-        if range.start < range.end {
-            covered_parts.push(range);
-        }
-    }
+    from_lines.chain(on_edges).find(|candidate| {
+        (0..=max_coord).contains(&candidate.x)
+            && (0..=max_coord).contains(&candidate.y)
+            && input.iter().all(|sensor| {
+                sensor.sensor_location.manhattan_distance(candidate) > sensor.range as u64
+            })
+    })
+}
+
+#[aoc(day15, part2)]
+fn part2(input: &Parsed) -> i64 {
+    tuning_frequency(find_beacon_geometric(input, unsafe { PART2_MAX_COORD_XY }).unwrap())
+}
 
-    let area = covered_parts.iter().fold(0, |a, x| a + x.end - x.start);
-    (area, covered_parts)
+#[aoc(day15, part2, RowScan)]
+fn part2_row_scan(input: &Parsed) -> i64 {
+    tuning_frequency(find_beacon_row_scan(input, unsafe { PART2_MAX_COORD_XY }).unwrap())
 }
 
 #[cfg(test)]
 mod tests {
     use std::fs::read_to_string;
 
+    use proptest::prelude::*;
+
     use super::*;
 
+    fn sensor(x: i64, y: i64, range: i64) -> Sensor {
+        Sensor {
+            sensor_location: Point { x, y },
+            beacon_location: Point { x: x + range, y },
+            range,
+        }
+    }
+
     fn input<'a>() -> &'a str {
         "Sensor at x=2, y=18: closest beacon is at x=-2, y=15
 Sensor at x=9, y=16: closest beacon is at x=10, y=16
@@ -193,6 +304,70 @@ Sensor at x=20, y=1: closest beacon is at x=15, y=3"
         assert_eq!(part2(&parse_input(input())), 56000011);
     }
 
+    #[test]
+    fn solvers_agree() {
+        let sensors = parse_input(input());
+        let expected = Some(Point { x: 14, y: 11 });
+        assert_eq!(find_beacon_row_scan(&sensors, 20), expected);
+        assert_eq!(find_beacon_geometric(&sensors, 20), expected);
+    }
+
+    #[test]
+    fn solvers_find_beacon_in_corner() {
+        let sensors = parse_input("Sensor at x=0, y=0: closest beacon is at x=7, y=0");
+        let expected = Some(Point { x: 4, y: 4 });
+        assert_eq!(find_beacon_geometric(&sensors, 4), expected);
+        assert_eq!(find_beacon_row_scan(&sensors, 4), expected);
+    }
+
+    #[test]
+    fn solvers_find_beacon_in_diagonal_gap() {
+        // (1, 2) is two steps outside the sensors at (3, 0) and (0, 4)
+        let sensors = vec![
+            sensor(2, 5, 3),
+            sensor(4, 4, 2),
+            sensor(3, 0, 2),
+            sensor(0, 0, 2),
+            sensor(5, 5, 4),
+            sensor(0, 4, 1),
+        ];
+        let expected = Some(Point { x: 1, y: 2 });
+        assert_eq!(find_beacon_geometric(&sensors, 5), expected);
+        assert_eq!(find_beacon_row_scan(&sensors, 5), expected);
+    }
+
+    #[test]
+    fn sensor_field_coverage() {
+        let field = SensorField::from(parse_input(input()));
+        assert_eq!(field.row_coverage(10).to_ranges(), vec![-2..25]);
+        assert_eq!(field.row_coverage(11).to_ranges(), vec![-3..14, 15..26]);
+        assert_eq!(field.column_coverage(14).to_ranges(), vec![0..11, 12..24]);
+
+        let sensor_8_7 = field.covering_sensor(&Point { x: 8, y: 0 }).unwrap();
+        assert_eq!(sensor_8_7.sensor_location, Point { x: 8, y: 7 });
+        assert!(!field.is_covered(&Point { x: 14, y: 11 }));
+        assert!(!field.is_covered(&Point { x: 30, y: 0 }));
+    }
+
+    #[test]
+    fn sensor_field_area() {
+        let field = SensorField::from(parse_input(input()));
+        assert_eq!(field.covered_area(0..21, 0..21), 21 * 21 - 1);
+        assert_eq!(
+            field.uncovered_points(0..21, 0..21),
+            vec![Point { x: 14, y: 11 }]
+        );
+        assert_eq!(field.covered_area(-10..0, 10..11), 2);
+        assert_eq!(
+            field.uncovered_points(24..28, 10..11),
+            vec![
+                Point { x: 25, y: 10 },
+                Point { x: 26, y: 10 },
+                Point { x: 27, y: 10 }
+            ]
+        );
+    }
+
     #[test]
     fn input2() {
         assert_eq!(
@@ -202,4 +377,36 @@ Sensor at x=20, y=1: closest beacon is at x=15, y=3"
             11583882601918
         );
     }
+
+    proptest! {
+        // beacons in one-wide diagonal gaps are rare, so run extra cases
+        #![proptest_config(ProptestConfig {
+            cases: 2000,
+            max_global_rejects: 10000,
+            ..ProptestConfig::default()
+        })]
+
+        // Sensors reach up to two steps short of a chosen beacon; fields
+        // leaving any other point uncovered are skipped.
+        #[test]
+        fn solvers_agree_on_random_fields(
+            beacon in (0i64..=6, 0i64..=6),
+            sensors in prop::collection::vec((0i64..=6, 0i64..=6, 1i64..=2), 4..14),
+        ) {
+            let beacon = Point { x: beacon.0, y: beacon.1 };
+            let sensors = sensors
+                .into_iter()
+                .map(|(x, y, slack)| {
+                    let range = Point { x, y }.manhattan_distance(&beacon) as i64 - slack;
+                    sensor(x, y, range)
+                })
+                .filter(|sensor| sensor.range >= 0)
+                .collect_vec();
+            let field = SensorField::from(sensors.clone());
+            prop_assume!(field.uncovered_points(0..7, 0..7) == vec![beacon]);
+
+            prop_assert_eq!(find_beacon_geometric(&sensors, 6), Some(beacon));
+            prop_assert_eq!(find_beacon_row_scan(&sensors, 6), Some(beacon));
+        }
+    }
 }
@@ -1,16 +1,23 @@
-use std::{collections::HashSet, str::FromStr};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    io::BufRead,
+    str::FromStr,
+};
 
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::Itertools;
+use itertools::{process_results, Itertools};
 use pathfinding::prelude::connected_components;
 
+use crate::streaming::{records, StreamError};
+
 type Parsed = Vec<Position>;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-struct Position {
-    x: i32,
-    y: i32,
-    z: i32,
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
 }
 
 impl Position {
@@ -33,36 +40,68 @@ impl Position {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct InvalidPosition(pub String);
+
+impl Display for InvalidPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid position '{}'", self.0)
+    }
+}
+
+impl std::error::Error for InvalidPosition {}
+
 impl FromStr for Position {
-    type Err = ();
+    type Err = InvalidPosition;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (x, y, z) = s
-            .split(",")
-            .map(|s| s.parse().unwrap())
+            .split(',')
+            .map(|s| s.parse().ok())
             .collect_tuple()
-            .unwrap();
+            .and_then(|(x, y, z)| Some((x?, y?, z?)))
+            .ok_or_else(|| InvalidPosition(s.to_string()))?;
         Ok(Position { x, y, z })
     }
 }
 
+pub fn cubes<R: BufRead>(
+    reader: R,
+) -> impl Iterator<Item = Result<Position, StreamError<InvalidPosition>>> {
+    records(reader)
+}
+
 #[aoc_generator(day18)]
-fn parse_input(input: &str) -> Parsed {
-    input.lines().map(|line| line.parse().unwrap()).collect()
+fn parse_input(input: &str) -> Result<Parsed, StreamError<InvalidPosition>> {
+    cubes(input.as_bytes()).collect()
+}
+
+// Adds one cube at a time: each new cube exposes six faces and covers one
+// face of every cube it touches.
+pub fn surface_area(cubes: impl IntoIterator<Item = Position>) -> usize {
+    let mut seen = HashSet::new();
+    cubes.into_iter().fold(0, |area, cube| {
+        if !seen.insert(cube) {
+            return area;
+        }
+        let touching = cube
+            .neighbors()
+            .iter()
+            .filter(|neighbor| seen.contains(*neighbor))
+            .count();
+        area + 6 - 2 * touching
+    })
+}
+
+pub fn surface_area_streaming<R: BufRead>(
+    reader: R,
+) -> Result<usize, StreamError<InvalidPosition>> {
+    process_results(cubes(reader), |cubes| surface_area(cubes))
 }
 
 #[aoc(day18, part1)]
 fn part1(input: &Parsed) -> usize {
-    input
-        .iter()
-        .map(|cube| {
-            let neighbors = cube.neighbors();
-            neighbors
-                .iter()
-                .filter(|cube| !input.contains(*cube))
-                .count()
-        })
-        .sum()
+    surface_area(input.iter().copied())
 }
 
 #[aoc(day18, part2)]
@@ -128,11 +167,24 @@ mod tests {
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&parse_input(input())), 64);
+        assert_eq!(part1(&parse_input(input()).unwrap()), 64);
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&parse_input(input())), 58);
+        assert_eq!(part2(&parse_input(input()).unwrap()), 58);
+    }
+
+    #[test]
+    fn streaming() {
+        assert_eq!(surface_area_streaming(input().as_bytes()).unwrap(), 64);
+        assert_eq!(
+            surface_area_streaming("1,1,1\n2,1,1".as_bytes()).unwrap(),
+            10
+        );
+        assert!(matches!(
+            surface_area_streaming("1,1,1\n1,2".as_bytes()),
+            Err(StreamError::Parse { line: 2, .. })
+        ));
     }
 }
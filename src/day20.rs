@@ -1,14 +1,25 @@
+use std::{io::BufRead, num::ParseIntError};
+
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 
+use crate::streaming::{records, StreamError};
+
 type Parsed = Vec<i128>;
+pub type Mixer = fn(Vec<(usize, i128)>) -> Vec<(usize, i128)>;
+
+pub fn numbers<R: BufRead>(
+    reader: R,
+) -> impl Iterator<Item = Result<i128, StreamError<ParseIntError>>> {
+    records(reader)
+}
 
 #[aoc_generator(day20)]
-fn parse_input(input: &str) -> Parsed {
-    input.lines().map(|line| line.parse().unwrap()).collect()
+fn parse_input(input: &str) -> Result<Parsed, StreamError<ParseIntError>> {
+    numbers(input.as_bytes()).collect()
 }
 
-fn encryption_mix(numbers: Vec<(usize, i128)>) -> Vec<(usize, i128)> {
+pub fn encryption_mix(numbers: Vec<(usize, i128)>) -> Vec<(usize, i128)> {
     let mut numbers = numbers;
     let length = numbers.len();
     let max_index = length as i128 - 1;
@@ -40,42 +51,294 @@ fn encryption_mix(numbers: Vec<(usize, i128)>) -> Vec<(usize, i128)> {
     numbers
 }
 
-fn calculate_solution(numbers: &Vec<(usize, i128)>) -> i128 {
-    let pos_0 = numbers.iter().position(|(_, x)| *x == 0).unwrap();
+#[derive(Debug, Clone)]
+struct MixNode {
+    value: (usize, i128),
+    priority: u64,
+    size: usize,
+    left: Option<usize>,
+    right: Option<usize>,
+    parent: Option<usize>,
+}
+
+// Implicit treap keyed by position, with parent links so a node's current
+// position can be recovered in O(log n).
+struct MixTree {
+    nodes: Vec<MixNode>,
+    root: Option<usize>,
+}
+
+impl MixTree {
+    fn new(numbers: &[(usize, i128)]) -> Self {
+        let mut seed = 0x9e37_79b9_7f4a_7c15_u64;
+        let nodes = numbers
+            .iter()
+            .map(|&value| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                MixNode {
+                    value,
+                    priority: seed,
+                    size: 1,
+                    left: None,
+                    right: None,
+                    parent: None,
+                }
+            })
+            .collect();
+
+        let mut tree = MixTree { nodes, root: None };
+        for node in 0..numbers.len() {
+            tree.root = tree.merge(tree.root, Some(node));
+        }
+        tree
+    }
+
+    fn size(&self, node: Option<usize>) -> usize {
+        node.map_or(0, |node| self.nodes[node].size)
+    }
+
+    fn update(&mut self, node: usize) {
+        let MixNode { left, right, .. } = self.nodes[node];
+        self.nodes[node].size = 1 + self.size(left) + self.size(right);
+        for child in [left, right].into_iter().flatten() {
+            self.nodes[child].parent = Some(node);
+        }
+    }
+
+    fn merge(&mut self, left: Option<usize>, right: Option<usize>) -> Option<usize> {
+        match (left, right) {
+            (None, node) | (node, None) => node,
+            (Some(l), Some(r)) => {
+                if self.nodes[l].priority > self.nodes[r].priority {
+                    self.nodes[l].right = self.merge(self.nodes[l].right, Some(r));
+                    self.update(l);
+                    Some(l)
+                } else {
+                    self.nodes[r].left = self.merge(Some(l), self.nodes[r].left);
+                    self.update(r);
+                    Some(r)
+                }
+            }
+        }
+    }
+
+    fn split(&mut self, node: Option<usize>, count: usize) -> (Option<usize>, Option<usize>) {
+        let Some(node) = node else {
+            return (None, None);
+        };
+        self.nodes[node].parent = None;
+
+        let left_size = self.size(self.nodes[node].left);
+        if count <= left_size {
+            let (l, r) = self.split(self.nodes[node].left, count);
+            self.nodes[node].left = r;
+            self.update(node);
+            (l, Some(node))
+        } else {
+            let (l, r) = self.split(self.nodes[node].right, count - left_size - 1);
+            self.nodes[node].right = l;
+            self.update(node);
+            (Some(node), r)
+        }
+    }
+
+    fn position(&self, node: usize) -> usize {
+        let mut position = self.size(self.nodes[node].left);
+        let mut current = node;
+        while let Some(parent) = self.nodes[current].parent {
+            if self.nodes[parent].right == Some(current) {
+                position += self.size(self.nodes[parent].left) + 1;
+            }
+            current = parent;
+        }
+        position
+    }
+
+    fn move_node(&mut self, node: usize, to: usize) {
+        let from = self.position(node);
+        let (left, rest) = self.split(self.root, from);
+        let (_, right) = self.split(rest, 1);
+        let remaining = self.merge(left, right);
+
+        let (left, right) = self.split(remaining, to);
+        let left = self.merge(left, Some(node));
+        self.root = self.merge(left, right);
+    }
+
+    fn collect(&self, node: Option<usize>, out: &mut Vec<(usize, i128)>) {
+        if let Some(node) = node {
+            self.collect(self.nodes[node].left, out);
+            out.push(self.nodes[node].value);
+            self.collect(self.nodes[node].right, out);
+        }
+    }
+
+    fn into_vec(self) -> Vec<(usize, i128)> {
+        let mut out = Vec::with_capacity(self.nodes.len());
+        self.collect(self.root, &mut out);
+        out
+    }
+}
+
+fn reorder_tree(
+    numbers: Vec<(usize, i128)>,
+    reverse: bool,
+    target: fn(i128, i128, i128) -> i128,
+) -> Vec<(usize, i128)> {
+    let length = numbers.len();
+    if length < 2 {
+        return numbers;
+    }
+    let max_index = length as i128 - 1;
+
+    let mut node_of = vec![0; length];
+    for (node, (index, _)) in numbers.iter().enumerate() {
+        node_of[*index] = node;
+    }
+    if reverse {
+        node_of.reverse();
+    }
+
+    let mut tree = MixTree::new(&numbers);
+    for &node in &node_of {
+        let number = tree.nodes[node].value.1;
+        if number == 0 {
+            continue;
+        }
+
+        let index = tree.position(node) as i128;
+        tree.move_node(node, target(index, number, max_index) as usize);
+    }
+
+    tree.into_vec()
+}
+
+pub fn encryption_mix_tree(numbers: Vec<(usize, i128)>) -> Vec<(usize, i128)> {
+    reorder_tree(numbers, false, |index, number, max_index| {
+        match (index + number).rem_euclid(max_index) {
+            0 => max_index,
+            new_index => new_index,
+        }
+    })
+}
+
+// Undoes one round of `encryption_mix`. The list is circular, so the result
+// only matches the unmixed sequence up to rotation.
+pub fn encryption_unmix(numbers: Vec<(usize, i128)>) -> Vec<(usize, i128)> {
+    reorder_tree(numbers, true, |index, number, max_index| {
+        (index - number).rem_euclid(max_index)
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptionParams {
+    pub key: i128,
+    pub rounds: usize,
+    pub offsets: Vec<usize>,
+}
+
+impl Default for DecryptionParams {
+    fn default() -> Self {
+        Self {
+            key: 1,
+            rounds: 1,
+            offsets: vec![1000, 2000, 3000],
+        }
+    }
+}
 
-    let one = numbers[(pos_0 + 1000) % numbers.len()].1;
-    let two = numbers[(pos_0 + 2000) % numbers.len()].1;
-    let three = numbers[(pos_0 + 3000) % numbers.len()].1;
+impl DecryptionParams {
+    pub fn new(key: i128, rounds: usize) -> Self {
+        Self {
+            key,
+            rounds,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_offsets(self, offsets: Vec<usize>) -> Self {
+        Self { offsets, ..self }
+    }
+
+    pub fn mix(&self, input: &[i128], mix: Mixer) -> Vec<(usize, i128)> {
+        let mut numbers = input
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (i, (x * self.key)))
+            .collect_vec();
+
+        for _ in 0..self.rounds {
+            numbers = mix(numbers);
+        }
+
+        numbers
+    }
 
-    one + two + three
+    pub fn unmix(&self, mixed: Vec<(usize, i128)>) -> Vec<(usize, i128)> {
+        let mut numbers = mixed;
+
+        for _ in 0..self.rounds {
+            numbers = encryption_unmix(numbers);
+        }
+
+        numbers
+            .into_iter()
+            .map(|(i, x)| (i, x / self.key))
+            .collect()
+    }
+
+    pub fn grove_coordinates(&self, numbers: &[(usize, i128)]) -> Vec<i128> {
+        let pos_0 = numbers.iter().position(|(_, x)| *x == 0).unwrap();
+
+        self.offsets
+            .iter()
+            .map(|offset| numbers[(pos_0 + offset) % numbers.len()].1)
+            .collect()
+    }
+
+    pub fn decrypt(&self, input: &[i128], mix: Mixer) -> i128 {
+        self.grove_coordinates(&self.mix(input, mix)).iter().sum()
+    }
+
+    // Mixing needs every number, so only parsing is incremental here.
+    pub fn decrypt_streaming<R: BufRead>(
+        &self,
+        reader: R,
+        mix: Mixer,
+    ) -> Result<i128, StreamError<ParseIntError>> {
+        let input: Vec<_> = numbers(reader).try_collect()?;
+        Ok(self.decrypt(&input, mix))
+    }
 }
 
+pub const DECRYPTION_KEY: i128 = 811589153;
+
 #[aoc(day20, part1)]
 fn part1(input: &Parsed) -> i128 {
-    let numbers = input.iter().enumerate().map(|(i, x)| (i, *x)).collect_vec();
-    let numbers = encryption_mix(numbers);
+    DecryptionParams::default().decrypt(input, encryption_mix_tree)
+}
 
-    calculate_solution(&numbers)
+#[aoc(day20, part1, Vec)]
+fn part1_vec(input: &Parsed) -> i128 {
+    DecryptionParams::default().decrypt(input, encryption_mix)
 }
 
 #[aoc(day20, part2)]
 fn part2(input: &Parsed) -> i128 {
-    let key = 811589153;
-    let mut numbers = input
-        .iter()
-        .enumerate()
-        .map(|(i, x)| (i, (x * key)))
-        .collect_vec();
-
-    for _ in 0..10 {
-        numbers = encryption_mix(numbers);
-    }
+    DecryptionParams::new(DECRYPTION_KEY, 10).decrypt(input, encryption_mix_tree)
+}
 
-    calculate_solution(&numbers)
+#[aoc(day20, part2, Vec)]
+fn part2_vec(input: &Parsed) -> i128 {
+    DecryptionParams::new(DECRYPTION_KEY, 10).decrypt(input, encryption_mix)
 }
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
     fn input<'a>() -> &'a str {
@@ -90,11 +353,99 @@ mod tests {
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&parse_input(input())), 3);
+        assert_eq!(part1(&parse_input(input()).unwrap()), 3);
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&parse_input(input())), 1623178306);
+        assert_eq!(part2(&parse_input(input()).unwrap()), 1623178306);
+    }
+
+    #[test]
+    fn sample_vec() {
+        assert_eq!(part1_vec(&parse_input(input()).unwrap()), 3);
+        assert_eq!(part2_vec(&parse_input(input()).unwrap()), 1623178306);
+    }
+
+    #[test]
+    fn streaming() {
+        let params = DecryptionParams::new(DECRYPTION_KEY, 10);
+        assert_eq!(
+            params
+                .decrypt_streaming(input().as_bytes(), encryption_mix_tree)
+                .unwrap(),
+            1623178306
+        );
+        assert!(matches!(
+            params.decrypt_streaming("1\n2\n1.5".as_bytes(), encryption_mix_tree),
+            Err(StreamError::Parse { line: 3, .. })
+        ));
+    }
+
+    fn rotate_to_first(numbers: &[(usize, i128)]) -> Vec<(usize, i128)> {
+        let start = numbers.iter().position(|(i, _)| *i == 0).unwrap();
+        numbers[start..]
+            .iter()
+            .chain(&numbers[..start])
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn sample_mix_vector() {
+        let mixed =
+            DecryptionParams::default().mix(&parse_input(input()).unwrap(), encryption_mix_tree);
+        assert_eq!(
+            mixed.iter().map(|(_, x)| *x).collect_vec(),
+            vec![1, 2, -3, 4, 0, 3, -2]
+        );
+    }
+
+    #[test]
+    fn sample_unmix() {
+        let input = parse_input(input()).unwrap();
+        let params = DecryptionParams::new(DECRYPTION_KEY, 10);
+        let unmixed = params.unmix(params.mix(&input, encryption_mix_tree));
+        assert_eq!(
+            rotate_to_first(&unmixed),
+            input.iter().copied().enumerate().collect_vec()
+        );
+    }
+
+    #[test]
+    fn custom_offsets() {
+        let params = DecryptionParams::default().with_offsets(vec![1, 3]);
+        let mixed = params.mix(&parse_input(input()).unwrap(), encryption_mix_tree);
+        assert_eq!(params.grove_coordinates(&mixed), vec![3, 1]);
+        assert_eq!(
+            params.decrypt(&parse_input(input()).unwrap(), encryption_mix),
+            4
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn tree_mix_matches_vec_mix(values in prop::collection::vec(-50i128..50, 2..60), rounds in 1usize..4) {
+            let mut expected = values.iter().copied().enumerate().collect_vec();
+            let mut actual = expected.clone();
+            for _ in 0..rounds {
+                expected = encryption_mix(expected);
+                actual = encryption_mix_tree(actual);
+                prop_assert_eq!(&actual, &expected);
+            }
+        }
+
+        #[test]
+        fn tree_mix_matches_vec_mix_large_values(values in prop::collection::vec(-1_000_000_000_000i128..1_000_000_000_000, 2..30)) {
+            let numbers = values.iter().copied().enumerate().collect_vec();
+            prop_assert_eq!(encryption_mix_tree(numbers.clone()), encryption_mix(numbers));
+        }
+
+        #[test]
+        fn unmix_reverses_mix(values in prop::collection::vec(-50i128..50, 2..60), key in 1i128..1000, rounds in 1usize..4) {
+            let params = DecryptionParams::new(key, rounds);
+            let unmixed = params.unmix(params.mix(&values, encryption_mix_tree));
+            prop_assert_eq!(rotate_to_first(&unmixed), values.iter().copied().enumerate().collect_vec());
+        }
     }
 }
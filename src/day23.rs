@@ -1,7 +1,7 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::Itertools;
+use itertools::{iproduct, Itertools};
 
 type Parsed = HashMap<(i32, i32), Tile>;
 
@@ -11,6 +11,10 @@ enum Tile {
     Empty,
 }
 
+pub type ElfId = usize;
+pub type ElfMove = ((i32, i32), (i32, i32));
+type Track = Vec<(usize, (i32, i32))>;
+
 #[aoc_generator(day23)]
 fn parse_input(input: &str) -> Parsed {
     input
@@ -18,10 +22,12 @@ fn parse_input(input: &str) -> Parsed {
         .enumerate()
         .flat_map(|(y, line)| {
             line.chars().enumerate().filter_map(move |(x, c)| match c {
-                '#' => Some(((x as i32, y as i32), Tile::Elf(y * 100 + x))),
+                '#' => Some((x as i32, y as i32)),
                 _ => None,
             })
         })
+        .enumerate()
+        .map(|(id, position)| (position, Tile::Elf(id)))
         .collect()
 }
 
@@ -51,7 +57,7 @@ fn calculate_proposed_positions(
     field
         .iter()
         .map(|(pos, elf)| {
-            let neighbors = neighbors(&field, *pos);
+            let neighbors = neighbors(field, *pos);
             if neighbors.iter().all(|(_, n)| n == &Tile::Empty) {
                 return (*elf, *pos);
             }
@@ -66,121 +72,873 @@ fn calculate_proposed_positions(
                 }
             }
 
-            return (*elf, *pos);
+            (*elf, *pos)
         })
         .collect()
 }
 
-#[aoc(day23, part1)]
-fn part1(input: &Parsed) -> usize {
-    let mut field = input.clone();
+fn step_hashmap(field: &mut Parsed, preferred_directions: &mut VecDeque<[usize; 3]>) -> usize {
+    let proposed_positions = calculate_proposed_positions(field, preferred_directions);
 
-    let mut preferred_directions: VecDeque<[usize; 3]> =
-        vec![[1, 2, 3], [5, 6, 7], [1, 0, 7], [3, 4, 5]].into();
+    let mut proposal_counts = HashMap::<(i32, i32), usize>::new();
+    for position in proposed_positions.values() {
+        *proposal_counts.entry(*position).or_default() += 1;
+    }
+    let previous_positions: HashMap<Tile, (i32, i32)> = field
+        .iter()
+        .map(|(position, elf)| (*elf, *position))
+        .collect();
+
+    let mut moved = 0;
+    for (elf, position) in &proposed_positions {
+        let prev_pos = previous_positions[elf];
+        if proposal_counts[position] > 1 || *position == prev_pos {
+            continue;
+        }
+
+        field.remove(&prev_pos);
+        field.insert(*position, *elf);
+        moved += 1;
+    }
+
+    preferred_directions.rotate_left(1);
+    moved
+}
+
+fn hashmap_directions() -> VecDeque<[usize; 3]> {
+    vec![[1, 2, 3], [5, 6, 7], [1, 0, 7], [3, 4, 5]].into()
+}
+
+#[aoc(day23, part1, HashMap)]
+fn part1_hashmap(input: &Parsed) -> usize {
+    let mut field = input.clone();
+    let mut preferred_directions = hashmap_directions();
 
     for _ in 0..10 {
-        let proposed_directions = calculate_proposed_positions(&field, &preferred_directions);
+        step_hashmap(&mut field, &mut preferred_directions);
+    }
+
+    BoundingBox::around(field.keys().copied()).unwrap().area() - field.len()
+}
+
+#[aoc(day23, part2, HashMap)]
+fn part2_hashmap(input: &Parsed) -> usize {
+    let mut field = input.clone();
+    let mut preferred_directions = hashmap_directions();
+
+    (1..)
+        .find(|_| step_hashmap(&mut field, &mut preferred_directions) == 0)
+        .unwrap()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    South,
+    West,
+    East,
+}
+
+impl Direction {
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::South => (0, 1),
+            Direction::West => (-1, 0),
+            Direction::East => (1, 0),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub min: (i32, i32),
+    pub max: (i32, i32),
+}
+
+impl BoundingBox {
+    pub fn around(positions: impl IntoIterator<Item = (i32, i32)>) -> Option<Self> {
+        positions.into_iter().fold(None, |bounds, (x, y)| {
+            Some(match bounds {
+                None => BoundingBox {
+                    min: (x, y),
+                    max: (x, y),
+                },
+                Some(BoundingBox { min, max }) => BoundingBox {
+                    min: (min.0.min(x), min.1.min(y)),
+                    max: (max.0.max(x), max.1.max(y)),
+                },
+            })
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        (self.max.0 - self.min.0 + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max.1 - self.min.1 + 1) as usize
+    }
+
+    pub fn area(&self) -> usize {
+        self.width() * self.height()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RoundStats {
+    pub round: usize,
+    pub moved: usize,
+    pub bounds: Option<BoundingBox>,
+}
+
+type Row = Vec<u128>;
+
+const LANE_BITS: usize = u128::BITS as usize;
+const ROW_GROWTH: usize = 16;
+
+// Moves every bit one column east (towards higher x).
+fn shift_east(row: &[u128]) -> Row {
+    let mut carry = 0;
+    row.iter()
+        .map(|&lane| {
+            let shifted = lane << 1 | carry;
+            carry = lane >> (LANE_BITS - 1);
+            shifted
+        })
+        .collect()
+}
+
+// Moves every bit one column west (towards lower x).
+fn shift_west(row: &[u128]) -> Row {
+    let mut carry = 0;
+    let mut shifted = vec![0; row.len()];
+    for (out, &lane) in shifted.iter_mut().zip(row).rev() {
+        *out = lane >> 1 | carry;
+        carry = lane << (LANE_BITS - 1);
+    }
+    shifted
+}
+
+fn combine(a: &[u128], b: &[u128], op: fn(u128, u128) -> u128) -> Row {
+    a.iter().zip(b).map(|(a, b)| op(*a, *b)).collect()
+}
+
+fn or(a: &[u128], b: &[u128]) -> Row {
+    combine(a, b, |a, b| a | b)
+}
+
+fn and(a: &[u128], b: &[u128]) -> Row {
+    combine(a, b, |a, b| a & b)
+}
+
+fn and_not(a: &[u128], b: &[u128]) -> Row {
+    combine(a, b, |a, b| a & !b)
+}
+
+fn count(row: &[u128]) -> usize {
+    row.iter().map(|lane| lane.count_ones() as usize).sum()
+}
+
+fn columns(row: &[u128]) -> impl Iterator<Item = usize> + '_ {
+    (0..row.len() * LANE_BITS)
+        .filter(|column| row[column / LANE_BITS] >> (column % LANE_BITS) & 1 == 1)
+}
+
+// Elves stored as one bitset per row, split into u128 lanes. The board grows
+// by whole lanes or blocks of rows so there is always an empty border around
+// the elves, which keeps every proposal inside the board.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitboard {
+    rows: Vec<Row>,
+    lanes: usize,
+    origin: (i32, i32),
+    directions: [Direction; 4],
+    round: usize,
+}
+
+impl Bitboard {
+    pub fn new(elves: impl IntoIterator<Item = (i32, i32)>) -> Self {
+        let elves = elves.into_iter().collect_vec();
+        let bounds = BoundingBox::around(elves.iter().copied()).unwrap_or(BoundingBox {
+            min: (0, 0),
+            max: (0, 0),
+        });
+
+        let mut board = Bitboard {
+            rows: vec![vec![0; 1]; bounds.height()],
+            lanes: 1,
+            origin: bounds.min,
+            directions: [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East,
+            ],
+            round: 0,
+        };
+        while board.lanes * LANE_BITS < bounds.width() {
+            board.push_lane();
+        }
+        for (x, y) in elves {
+            let column = (x - board.origin.0) as usize;
+            let row = (y - board.origin.1) as usize;
+            board.rows[row][column / LANE_BITS] |= 1 << (column % LANE_BITS);
+        }
+        board
+    }
 
-        let all_positions = proposed_directions.values().collect_vec();
-        proposed_directions.iter().for_each(|(elf, position)| {
-            if all_positions.iter().filter(|pos| **pos == position).count() > 1 {
-                return;
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.iter().map(|row| count(row)).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn elves(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.rows.iter().enumerate().flat_map(move |(y, row)| {
+            columns(row)
+                .map(move |column| (self.origin.0 + column as i32, self.origin.1 + y as i32))
+        })
+    }
+
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        let rows = self
+            .rows
+            .iter()
+            .positions(|row| row.iter().any(|lane| *lane != 0))
+            .collect_vec();
+        let (first_row, last_row) = (*rows.first()?, *rows.last()?);
+
+        let lowest_column = |row: &Row| {
+            row.iter()
+                .position(|lane| *lane != 0)
+                .map(|i| i * LANE_BITS + row[i].trailing_zeros() as usize)
+        };
+        let highest_column = |row: &Row| {
+            row.iter()
+                .rposition(|lane| *lane != 0)
+                .map(|i| i * LANE_BITS + LANE_BITS - 1 - row[i].leading_zeros() as usize)
+        };
+        let min_column = self.rows.iter().filter_map(lowest_column).min()?;
+        let max_column = self.rows.iter().filter_map(highest_column).max()?;
+
+        Some(BoundingBox {
+            min: (
+                self.origin.0 + min_column as i32,
+                self.origin.1 + first_row as i32,
+            ),
+            max: (
+                self.origin.0 + max_column as i32,
+                self.origin.1 + last_row as i32,
+            ),
+        })
+    }
+
+    pub fn empty_tiles(&self) -> usize {
+        self.bounds().map_or(0, |bounds| bounds.area()) - self.len()
+    }
+
+    fn push_lane(&mut self) {
+        self.rows.iter_mut().for_each(|row| row.push(0));
+        self.lanes += 1;
+    }
+
+    fn ensure_margin(&mut self) {
+        let empty = vec![0; self.lanes];
+        if self.rows.first().is_none_or(|row| *row != empty) {
+            self.rows
+                .splice(0..0, std::iter::repeat_n(empty.clone(), ROW_GROWTH));
+            self.origin.1 -= ROW_GROWTH as i32;
+        }
+        if self.rows.last() != Some(&empty) {
+            self.rows
+                .extend(std::iter::repeat_n(empty.clone(), ROW_GROWTH));
+        }
+        if self.rows.iter().any(|row| row[0] & 1 == 1) {
+            self.rows.iter_mut().for_each(|row| row.insert(0, 0));
+            self.lanes += 1;
+            self.origin.0 -= LANE_BITS as i32;
+        }
+        if self
+            .rows
+            .iter()
+            .any(|row| row[self.lanes - 1] >> (LANE_BITS - 1) == 1)
+        {
+            self.push_lane();
+        }
+    }
+
+    pub fn step(&mut self) -> RoundStats {
+        self.advance().0
+    }
+
+    // Like `step`, but also returns the (from, to) position of every elf that
+    // moved this round.
+    pub fn step_moves(&mut self) -> (RoundStats, Vec<ElfMove>) {
+        let (stats, succeeded) = self.advance();
+        let origin = self.origin;
+
+        let directions = [
+            Direction::North,
+            Direction::South,
+            Direction::West,
+            Direction::East,
+        ];
+        let moves = directions
+            .iter()
+            .zip(&succeeded)
+            .flat_map(|(direction, rows)| {
+                let (dx, dy) = direction.offset();
+                rows.iter().enumerate().flat_map(move |(y, row)| {
+                    columns(row).map(move |column| {
+                        let from = (origin.0 + column as i32, origin.1 + y as i32);
+                        (from, (from.0 + dx, from.1 + dy))
+                    })
+                })
+            })
+            .collect();
+
+        (stats, moves)
+    }
+
+    // Returns the stats and, per direction, the rows of elves that moved.
+    fn advance(&mut self) -> (RoundStats, [Vec<Row>; 4]) {
+        self.ensure_margin();
+
+        let height = self.rows.len();
+        let empty = vec![0; self.lanes];
+        let row = |rows: &[Row], y: usize, offset: isize| -> Row {
+            y.checked_add_signed(offset)
+                .and_then(|y| rows.get(y))
+                .unwrap_or(&empty)
+                .clone()
+        };
+
+        // proposals[direction][y]: elves in row y proposing to move that way
+        let mut proposals = [(); 4].map(|_| vec![empty.clone(); height]);
+        for (y, current) in self.rows.iter().enumerate() {
+            let (north, south) = (row(&self.rows, y, -1), row(&self.rows, y, 1));
+            let column = or(&or(&north, current), &south);
+            let occupied = |direction| match direction {
+                Direction::North => or(&or(&north, &shift_east(&north)), &shift_west(&north)),
+                Direction::South => or(&or(&south, &shift_east(&south)), &shift_west(&south)),
+                Direction::West => shift_east(&column),
+                Direction::East => shift_west(&column),
+            };
+            let occupied = [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East,
+            ]
+            .map(occupied);
+
+            let crowded = occupied.iter().fold(empty.clone(), |acc, o| or(&acc, o));
+            let mut remaining = and(current, &crowded);
+            for direction in self.directions {
+                let proposing = and_not(&remaining, &occupied[direction as usize]);
+                remaining = and_not(&remaining, &proposing);
+                proposals[direction as usize][y] = proposing;
             }
+        }
 
-            let prev_pos = *field
-                .iter()
-                .find_map(|(p, e)| if e == elf { Some(p) } else { None })
-                .unwrap();
-            if *position == prev_pos {
-                return;
+        // Only elves approaching a tile from opposite sides can collide.
+        let [north, south, west, east] = proposals;
+        let blocked_vertical = (0..height)
+            .map(|y| and(&row(&north, y, 1), &row(&south, y, -1)))
+            .collect_vec();
+        let blocked_horizontal = (0..height)
+            .map(|y| and(&shift_west(&west[y]), &shift_east(&east[y])))
+            .collect_vec();
+
+        let mut moved = 0;
+        let mut succeeded = [(); 4].map(|_| Vec::with_capacity(height));
+        for y in 0..height {
+            let moving = [
+                and_not(&north[y], &row(&blocked_vertical, y, -1)),
+                and_not(&south[y], &row(&blocked_vertical, y, 1)),
+                and_not(&west[y], &shift_east(&blocked_horizontal[y])),
+                and_not(&east[y], &shift_west(&blocked_horizontal[y])),
+            ];
+            for (succeeded, moving) in succeeded.iter_mut().zip(moving) {
+                moved += count(&moving);
+                succeeded.push(moving);
             }
+        }
 
-            field.remove(&prev_pos);
-            field.insert(*position, *elf);
-        });
+        let [north, south, west, east] = &succeeded;
+        let rows = (0..height)
+            .map(|y| {
+                let leaving = or(&or(&north[y], &south[y]), &or(&west[y], &east[y]));
+                let arriving = or(
+                    &or(&row(north, y, 1), &row(south, y, -1)),
+                    &or(&shift_west(&west[y]), &shift_east(&east[y])),
+                );
+                or(&and_not(&self.rows[y], &leaving), &arriving)
+            })
+            .collect();
+        self.rows = rows;
+        self.directions.rotate_left(1);
+        self.round += 1;
+
+        let stats = RoundStats {
+            round: self.round,
+            moved,
+            bounds: self.bounds(),
+        };
+        (stats, succeeded)
+    }
+}
+
+impl From<&Parsed> for Bitboard {
+    fn from(field: &Parsed) -> Self {
+        Bitboard::new(field.keys().copied())
+    }
+}
+
+// Runs the bitboard while following each elf, recording the round at which
+// it arrived at every position it has occupied.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    board: Bitboard,
+    elf_at: HashMap<(i32, i32), ElfId>,
+    tracks: HashMap<ElfId, Track>,
+}
+
+impl Simulation {
+    pub fn new(elves: impl IntoIterator<Item = (ElfId, (i32, i32))>) -> Self {
+        let elf_at: HashMap<_, _> = elves.into_iter().map(|(id, pos)| (pos, id)).collect();
+        let tracks = elf_at
+            .iter()
+            .map(|(pos, id)| (*id, vec![(0, *pos)]))
+            .collect();
+
+        Simulation {
+            board: Bitboard::new(elf_at.keys().copied()),
+            elf_at,
+            tracks,
+        }
+    }
+
+    pub fn board(&self) -> &Bitboard {
+        &self.board
+    }
+
+    pub fn round(&self) -> usize {
+        self.board.round()
+    }
 
-        let first = preferred_directions.pop_front().unwrap();
-        preferred_directions.push_back(first);
+    pub fn step(&mut self) -> RoundStats {
+        let (stats, moves) = self.board.step_moves();
+        for (from, to) in moves {
+            let elf = self.elf_at.remove(&from).unwrap();
+            self.elf_at.insert(to, elf);
+            self.tracks.get_mut(&elf).unwrap().push((stats.round, to));
+        }
+        stats
+    }
+
+    pub fn elf_at(&self, position: (i32, i32)) -> Option<ElfId> {
+        self.elf_at.get(&position).copied()
+    }
+
+    pub fn history(&self, elf: ElfId) -> Option<&[(usize, (i32, i32))]> {
+        self.tracks.get(&elf).map(|track| track.as_slice())
+    }
+
+    // Position of the elf at the end of the given round (0 being the start).
+    pub fn position_at(&self, elf: ElfId, round: usize) -> Option<(i32, i32)> {
+        if round > self.round() {
+            return None;
+        }
+        let track = self.tracks.get(&elf)?;
+        let arrived = track.partition_point(|(arrival, _)| *arrival <= round);
+        Some(track[arrived - 1].1)
+    }
 
-        // print_field(&field);
+    pub fn never_moved(&self) -> Vec<ElfId> {
+        self.tracks
+            .iter()
+            .filter(|(_, track)| track.len() == 1)
+            .map(|(elf, _)| *elf)
+            .sorted()
+            .collect()
     }
+}
 
-    // print_field(&field);
+impl From<&Parsed> for Simulation {
+    fn from(field: &Parsed) -> Self {
+        Simulation::new(field.iter().filter_map(|(position, tile)| match tile {
+            Tile::Elf(id) => Some((*id, *position)),
+            Tile::Empty => None,
+        }))
+    }
+}
 
-    let min_x = field.keys().min_by_key(|k| k.0).unwrap().0;
-    let max_x = field.keys().max_by_key(|k| k.0).unwrap().0;
-    let min_y = field.keys().min_by_key(|k| k.1).unwrap().1;
-    let max_y = field.keys().max_by_key(|k| k.1).unwrap().1;
+pub type Coord = [i32; 3];
 
-    let rect_size = (max_x - min_x + 1) * (max_y - min_y + 1);
-    let empty_tiles = rect_size as usize - field.len();
-    empty_tiles
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Neighbourhood {
+    // the puzzle's eight surrounding tiles
+    Square,
+    // axial hex coordinates (q, r)
+    Hex,
+    // all 26 surrounding cubes
+    Cube,
 }
 
-fn _print_field(field: &Parsed) {
-    let min_x = field.keys().min_by_key(|k| k.0).unwrap().0;
-    let max_x = field.keys().max_by_key(|k| k.0).unwrap().0;
-    let min_y = field.keys().min_by_key(|k| k.1).unwrap().1;
-    let max_y = field.keys().max_by_key(|k| k.1).unwrap().1;
+const HEX_DIRECTIONS: [Coord; 6] = [
+    [1, 0, 0],
+    [1, -1, 0],
+    [0, -1, 0],
+    [-1, 0, 0],
+    [-1, 1, 0],
+    [0, 1, 0],
+];
 
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            if let Some(_) = field.get(&(x, y)) {
-                print!("#");
-            } else {
-                print!(".")
-            }
+impl Neighbourhood {
+    pub fn offsets(self) -> Vec<Coord> {
+        match self {
+            Neighbourhood::Square => iproduct!(-1..=1, -1..=1)
+                .map(|(y, x)| [x, y, 0])
+                .filter(|offset| *offset != [0; 3])
+                .collect(),
+            Neighbourhood::Hex => HEX_DIRECTIONS.to_vec(),
+            Neighbourhood::Cube => iproduct!(-1..=1, -1..=1, -1..=1)
+                .map(|(z, y, x)| [x, y, z])
+                .filter(|offset| *offset != [0; 3])
+                .collect(),
         }
-        println!("");
     }
-    println!("");
 }
 
-#[aoc(day23, part2)]
-fn part2(input: &Parsed) -> usize {
-    let mut field = input.clone();
+// Move by `direction` if none of the `clear` offsets is occupied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub direction: Coord,
+    pub clear: Vec<Coord>,
+}
 
-    let mut preferred_directions: VecDeque<[usize; 3]> =
-        vec![[1, 2, 3], [5, 6, 7], [1, 0, 7], [3, 4, 5]].into();
+impl Rule {
+    pub fn new(direction: Coord, clear: Vec<Coord>) -> Self {
+        Self { direction, clear }
+    }
+}
 
-    let mut round = 1;
-    loop {
-        let proposed_directions = calculate_proposed_positions(&field, &preferred_directions);
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Topology {
+    Unbounded,
+    // coordinates wrap modulo the size on every axis; a 2D board has a
+    // size of 1 on the third axis
+    Torus { size: Coord },
+}
 
-        let all_positions = proposed_directions.values().collect_vec();
-        let mut moved = false;
-        proposed_directions.iter().for_each(|(elf, position)| {
-            if all_positions.iter().filter(|pos| **pos == position).count() > 1 {
-                return;
-            }
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StopCondition {
+    Rounds(usize),
+    NoMovement,
+    // the bounding box holds more than this many tiles
+    BoundsExceed(usize),
+}
 
-            let prev_pos = *field
-                .iter()
-                .find_map(|(p, e)| if e == elf { Some(p) } else { None })
-                .unwrap();
-            if *position == prev_pos {
-                return;
-            }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleSet {
+    pub neighbourhood: Neighbourhood,
+    pub rules: Vec<Rule>,
+    // move the first rule to the end after every round
+    pub rotate: bool,
+    // torus sizes are positive on every axis
+    topology: Topology,
+    // includes `Rounds` or `NoMovement`, so `Diffusion::run` stops even once the
+    // elves settle inside the bounds limit
+    stop: Vec<StopCondition>,
+}
 
-            field.remove(&prev_pos);
-            field.insert(*position, *elf);
-            moved = true;
-        });
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet::standard()
+    }
+}
+
+impl RuleSet {
+    pub fn standard() -> Self {
+        let rule = |direction: Direction| {
+            let (dx, dy) = direction.offset();
+            let clear = (-1..=1)
+                .map(|side| match direction {
+                    Direction::North | Direction::South => [side, dy, 0],
+                    Direction::West | Direction::East => [dx, side, 0],
+                })
+                .collect();
+            Rule::new([dx, dy, 0], clear)
+        };
+
+        RuleSet {
+            neighbourhood: Neighbourhood::Square,
+            rules: [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East,
+            ]
+            .map(rule)
+            .to_vec(),
+            rotate: true,
+            topology: Topology::Unbounded,
+            stop: vec![StopCondition::NoMovement],
+        }
+    }
+
+    // Each hex direction requires it and its two neighbouring directions to
+    // be clear.
+    pub fn hex() -> Self {
+        let rules = (0..6)
+            .map(|i| {
+                let clear = [5, 0, 1]
+                    .map(|side| HEX_DIRECTIONS[(i + side) % 6])
+                    .to_vec();
+                Rule::new(HEX_DIRECTIONS[i], clear)
+            })
+            .collect();
+
+        RuleSet {
+            neighbourhood: Neighbourhood::Hex,
+            rules,
+            ..RuleSet::standard()
+        }
+    }
+
+    // Each face direction requires the nine cubes on that side to be clear.
+    pub fn cube() -> Self {
+        let offsets = Neighbourhood::Cube.offsets();
+        let rules = iproduct!(0..3, [1, -1])
+            .map(|(axis, sign)| {
+                let mut direction = [0; 3];
+                direction[axis] = sign;
+                let clear = offsets
+                    .iter()
+                    .filter(|offset| offset[axis] == sign)
+                    .copied()
+                    .collect();
+                Rule::new(direction, clear)
+            })
+            .collect();
+
+        RuleSet {
+            neighbourhood: Neighbourhood::Cube,
+            rules,
+            ..RuleSet::standard()
+        }
+    }
+
+    pub fn with_rules(self, rules: Vec<Rule>) -> Self {
+        Self { rules, ..self }
+    }
+
+    pub fn rotating(self, rotate: bool) -> Self {
+        Self { rotate, ..self }
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    // 2D boards need `size[2] == 1`.
+    pub fn on_torus(self, size: Coord) -> Option<Self> {
+        size.iter().all(|length| *length > 0).then_some(Self {
+            topology: Topology::Torus { size },
+            ..self
+        })
+    }
+
+    pub fn stop(&self) -> &[StopCondition] {
+        &self.stop
+    }
 
-        if !moved {
-            return round;
+    pub fn stopping_when(self, stop: Vec<StopCondition>) -> Option<Self> {
+        stop.iter()
+            .any(|condition| !matches!(condition, StopCondition::BoundsExceed(_)))
+            .then_some(Self { stop, ..self })
+    }
+
+    fn wrap(&self, position: Coord) -> Coord {
+        match self.topology {
+            Topology::Unbounded => position,
+            Topology::Torus { size } => [0, 1, 2].map(|axis| position[axis].rem_euclid(size[axis])),
         }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub rounds: usize,
+    pub reason: StopCondition,
+}
+
+// General but slower counterpart to `Bitboard` driven by a `RuleSet`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diffusion {
+    rules: RuleSet,
+    elves: HashSet<Coord>,
+    round: usize,
+}
 
-        let first = preferred_directions.pop_front().unwrap();
-        preferred_directions.push_back(first);
+impl Diffusion {
+    pub fn new(rules: RuleSet, elves: impl IntoIterator<Item = Coord>) -> Self {
+        let elves = elves.into_iter().map(|elf| rules.wrap(elf)).collect();
+        Self {
+            rules,
+            elves,
+            round: 0,
+        }
+    }
+
+    pub fn elves(&self) -> &HashSet<Coord> {
+        &self.elves
+    }
+
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    pub fn bounds(&self) -> Option<(Coord, Coord)> {
+        let first = *self.elves.iter().next()?;
+        Some(self.elves.iter().fold((first, first), |(min, max), elf| {
+            (
+                [0, 1, 2].map(|axis| min[axis].min(elf[axis])),
+                [0, 1, 2].map(|axis| max[axis].max(elf[axis])),
+            )
+        }))
+    }
+
+    pub fn volume(&self) -> usize {
+        self.bounds().map_or(0, |(min, max)| {
+            (0..3)
+                .map(|axis| (max[axis] - min[axis] + 1) as usize)
+                .product()
+        })
+    }
 
-        // print_field(&field);
-        round += 1;
+    pub fn empty_tiles(&self) -> usize {
+        self.volume() - self.elves.len()
+    }
+
+    fn neighbour(&self, elf: &Coord, offset: &Coord) -> Coord {
+        self.rules
+            .wrap([0, 1, 2].map(|axis| elf[axis] + offset[axis]))
+    }
+
+    fn propose(&self, elf: &Coord, offsets: &[Coord]) -> Option<Coord> {
+        let occupied = |offset| self.elves.contains(&self.neighbour(elf, offset));
+        if !offsets.iter().any(occupied) {
+            return None;
+        }
+
+        let rules = &self.rules.rules;
+        let first = if self.rules.rotate {
+            self.round % rules.len().max(1)
+        } else {
+            0
+        };
+        rules[first..]
+            .iter()
+            .chain(&rules[..first])
+            .find(|rule| !rule.clear.iter().any(occupied))
+            .map(|rule| self.neighbour(elf, &rule.direction))
+            .filter(|target| !self.elves.contains(target))
+    }
+
+    pub fn step(&mut self) -> usize {
+        let offsets = self.rules.neighbourhood.offsets();
+        let proposals = self
+            .elves
+            .iter()
+            .filter_map(|elf| Some((*elf, self.propose(elf, &offsets)?)))
+            .collect_vec();
+        let targets = proposals.iter().map(|(_, target)| *target).counts();
+
+        let moves = proposals
+            .into_iter()
+            .filter(|(_, target)| targets[target] == 1)
+            .collect_vec();
+        for (from, _) in &moves {
+            self.elves.remove(from);
+        }
+        self.elves.extend(moves.iter().map(|(_, to)| *to));
+
+        self.round += 1;
+        moves.len()
+    }
+
+    // Steps until one of the rule set's stop conditions holds.
+    pub fn run(&mut self) -> Outcome {
+        loop {
+            let limit = self.rules.stop.iter().find(
+                |condition| matches!(condition, StopCondition::Rounds(rounds) if self.round >= *rounds),
+            );
+            if let Some(reason) = limit {
+                return Outcome {
+                    rounds: self.round,
+                    reason: *reason,
+                };
+            }
+
+            let moved = self.step();
+            let volume = self.volume();
+            let reason = self.rules.stop.iter().find(|condition| match condition {
+                StopCondition::Rounds(_) => false,
+                StopCondition::NoMovement => moved == 0,
+                StopCondition::BoundsExceed(limit) => volume > *limit,
+            });
+            if let Some(reason) = reason {
+                return Outcome {
+                    rounds: self.round,
+                    reason: *reason,
+                };
+            }
+        }
     }
 }
 
+impl From<&Parsed> for Diffusion {
+    fn from(field: &Parsed) -> Self {
+        Diffusion::new(RuleSet::standard(), field.keys().map(|(x, y)| [*x, *y, 0]))
+    }
+}
+
+#[aoc(day23, part1)]
+fn part1(input: &Parsed) -> usize {
+    let mut board = Bitboard::from(input);
+    for _ in 0..10 {
+        board.step();
+    }
+    board.empty_tiles()
+}
+
+#[aoc(day23, part2)]
+fn part2(input: &Parsed) -> usize {
+    let mut board = Bitboard::from(input);
+    std::iter::repeat_with(|| board.step())
+        .find(|stats| stats.moved == 0)
+        .unwrap()
+        .round
+}
+
+#[aoc(day23, part2, Configured)]
+fn part2_configured(input: &Parsed) -> usize {
+    Diffusion::from(input).run().rounds
+}
+
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
     fn input<'a>() -> &'a str {
@@ -202,4 +960,254 @@ mod tests {
     fn sample2() {
         assert_eq!(part2(&parse_input(input())), 20);
     }
+
+    #[test]
+    fn sample_hashmap() {
+        assert_eq!(part1_hashmap(&parse_input(input())), 110);
+        assert_eq!(part2_hashmap(&parse_input(input())), 20);
+    }
+
+    #[test]
+    fn small_example() {
+        let mut board = Bitboard::from(&parse_input(".....\n..##.\n..#..\n.....\n..##.\n....."));
+        let stats = (0..3).map(|_| board.step()).collect_vec();
+        assert_eq!(stats.iter().map(|s| s.moved).collect_vec(), vec![3, 5, 3]);
+        assert_eq!(
+            board.elves().sorted().collect_vec(),
+            vec![(0, 2), (2, 0), (2, 5), (4, 1), (4, 3)]
+        );
+        assert_eq!(
+            stats[2].bounds,
+            Some(BoundingBox {
+                min: (0, 0),
+                max: (4, 5)
+            })
+        );
+        assert_eq!(board.step().moved, 0);
+    }
+
+    #[test]
+    fn elf_identities() {
+        let field = parse_input(&format!("#{}#\n#", ".".repeat(99)));
+        assert_eq!(field.len(), 3);
+        assert_eq!(field.values().unique().count(), 3);
+
+        let simulation = Simulation::from(&field);
+        assert_eq!(simulation.elf_at((100, 0)), Some(1));
+        assert_eq!(simulation.elf_at((0, 1)), Some(2));
+    }
+
+    #[test]
+    fn movement_history() {
+        let mut simulation =
+            Simulation::from(&parse_input(".....\n..##.\n..#..\n.....\n..##.\n....."));
+        simulation.step();
+        assert_eq!(simulation.never_moved(), vec![2, 3]);
+        simulation.step();
+        simulation.step();
+        assert_eq!(simulation.never_moved(), vec![]);
+
+        assert_eq!(
+            (0..=3)
+                .map(|round| simulation.position_at(0, round).unwrap())
+                .collect_vec(),
+            vec![(2, 1), (2, 0), (2, 1), (2, 0)]
+        );
+        assert_eq!(simulation.position_at(3, 1), Some((2, 4)));
+        assert_eq!(simulation.position_at(3, 3), Some((2, 5)));
+        assert_eq!(simulation.position_at(3, 4), None);
+        assert_eq!(simulation.position_at(5, 0), None);
+        assert_eq!(
+            simulation.history(4),
+            Some([(0, (3, 4)), (1, (3, 3)), (2, (4, 3))].as_slice())
+        );
+        assert_eq!(simulation.elf_at((0, 2)), Some(2));
+    }
+
+    #[test]
+    fn simulation_tracks_board() {
+        let mut simulation = Simulation::from(&parse_input(input()));
+        for _ in 0..20 {
+            simulation.step();
+        }
+        let tracked = (0..simulation.board().len())
+            .map(|elf| simulation.position_at(elf, 20).unwrap())
+            .sorted()
+            .collect_vec();
+        assert_eq!(tracked, simulation.board().elves().sorted().collect_vec());
+    }
+
+    #[test]
+    fn configured_standard_rules() {
+        assert_eq!(RuleSet::standard().stopping_when(vec![]), None);
+        assert_eq!(
+            RuleSet::standard().stopping_when(vec![StopCondition::BoundsExceed(100)]),
+            None
+        );
+
+        let rules = RuleSet::standard()
+            .stopping_when(vec![StopCondition::Rounds(10)])
+            .unwrap();
+        let mut diffusion =
+            Diffusion::new(rules, parse_input(input()).keys().map(|(x, y)| [*x, *y, 0]));
+        assert_eq!(
+            diffusion.run(),
+            Outcome {
+                rounds: 10,
+                reason: StopCondition::Rounds(10)
+            }
+        );
+        assert_eq!(diffusion.empty_tiles(), 110);
+        assert_eq!(part2_configured(&parse_input(input())), 20);
+    }
+
+    #[test]
+    fn configured_bounds_limit() {
+        let mut board = Bitboard::from(&parse_input(input()));
+        let expected = std::iter::repeat_with(|| board.step())
+            .find(|stats| stats.bounds.unwrap().area() > 100)
+            .unwrap()
+            .round;
+
+        let rules = RuleSet::standard()
+            .stopping_when(vec![
+                StopCondition::NoMovement,
+                StopCondition::BoundsExceed(100),
+            ])
+            .unwrap();
+        let mut diffusion =
+            Diffusion::new(rules, parse_input(input()).keys().map(|(x, y)| [*x, *y, 0]));
+        assert_eq!(
+            diffusion.run(),
+            Outcome {
+                rounds: expected,
+                reason: StopCondition::BoundsExceed(100)
+            }
+        );
+    }
+
+    #[test]
+    fn configured_bounds_limit_settled() {
+        let rules = RuleSet::standard()
+            .stopping_when(vec![
+                StopCondition::BoundsExceed(100),
+                StopCondition::NoMovement,
+            ])
+            .unwrap();
+        let mut diffusion = Diffusion::new(rules, [[0, 0, 0]]);
+        assert_eq!(
+            diffusion.run(),
+            Outcome {
+                rounds: 1,
+                reason: StopCondition::NoMovement
+            }
+        );
+    }
+
+    #[test]
+    fn configured_rule_order() {
+        // always try east first, then west
+        let rules = RuleSet::standard()
+            .with_rules(vec![
+                Rule::new([1, 0, 0], vec![[1, -1, 0], [1, 0, 0], [1, 1, 0]]),
+                Rule::new([-1, 0, 0], vec![[-1, -1, 0], [-1, 0, 0], [-1, 1, 0]]),
+            ])
+            .rotating(false);
+        let mut diffusion = Diffusion::new(rules, [[0, 0, 0], [1, 0, 0]]);
+        assert_eq!(diffusion.run().rounds, 2);
+        assert_eq!(diffusion.elves(), &HashSet::from([[-1, 0, 0], [2, 0, 0]]));
+    }
+
+    #[test]
+    fn configured_hex() {
+        let mut diffusion = Diffusion::new(RuleSet::hex(), [[0, 0, 0], [1, 0, 0]]);
+        assert_eq!(diffusion.step(), 2);
+        assert_eq!(diffusion.elves(), &HashSet::from([[0, -1, 0], [2, 0, 0]]));
+        assert_eq!(
+            diffusion.run(),
+            Outcome {
+                rounds: 2,
+                reason: StopCondition::NoMovement
+            }
+        );
+    }
+
+    #[test]
+    fn configured_cube() {
+        assert_eq!(RuleSet::cube().rules.len(), 6);
+        assert!(RuleSet::cube()
+            .rules
+            .iter()
+            .all(|rule| rule.clear.len() == 9));
+
+        let rules = RuleSet::cube()
+            .with_rules(vec![
+                Rule::new([0, 0, -1], vec![[0, 0, -1]]),
+                Rule::new([0, 0, 1], vec![[0, 0, 1]]),
+            ])
+            .rotating(false);
+        let mut diffusion = Diffusion::new(rules, [[0, 0, 0], [0, 0, 1]]);
+        assert_eq!(diffusion.run().rounds, 2);
+        assert_eq!(diffusion.bounds(), Some(([0, 0, -1], [0, 0, 2])));
+        assert_eq!(diffusion.empty_tiles(), 2);
+    }
+
+    #[test]
+    fn configured_torus() {
+        assert_eq!(RuleSet::standard().on_torus([5, 5, 0]), None);
+        assert_eq!(RuleSet::standard().on_torus([5, -5, 1]), None);
+
+        let rules = RuleSet::standard()
+            .on_torus([5, 5, 1])
+            .unwrap()
+            .stopping_when(vec![StopCondition::NoMovement, StopCondition::Rounds(3)])
+            .unwrap();
+        let mut diffusion = Diffusion::new(rules, [[0, 0, 0], [4, 0, 0]]);
+        assert_eq!(diffusion.step(), 2);
+        assert_eq!(diffusion.elves(), &HashSet::from([[0, 4, 0], [4, 4, 0]]));
+        assert_eq!(
+            diffusion.run(),
+            Outcome {
+                rounds: 3,
+                reason: StopCondition::Rounds(3)
+            }
+        );
+    }
+
+    #[test]
+    fn board_grows_across_lanes() {
+        let elves = (0..140).map(|x| (x, 0)).collect_vec();
+        let mut board = Bitboard::new(elves.clone());
+        assert_eq!(board.step().moved, 140);
+        assert_eq!(board.len(), 140);
+        assert_eq!(board.bounds().unwrap().width(), 140);
+    }
+
+    proptest! {
+        #[test]
+        fn bitboard_matches_hashmap(cells in prop::collection::vec(prop::bool::weighted(0.4), 1..400), width in 1usize..20, rounds in 1usize..15) {
+            let mut field: Parsed = cells
+                .iter()
+                .enumerate()
+                .filter(|(_, elf)| **elf)
+                .enumerate()
+                .map(|(id, (i, _))| (((i % width) as i32, (i / width) as i32), Tile::Elf(id)))
+                .collect();
+            let mut board = Bitboard::from(&field);
+            let mut diffusion = Diffusion::from(&field);
+            let mut preferred_directions = hashmap_directions();
+
+            for _ in 0..rounds {
+                let expected = step_hashmap(&mut field, &mut preferred_directions);
+                let stats = board.step();
+                prop_assert_eq!(stats.moved, expected);
+                prop_assert_eq!(diffusion.step(), expected);
+                prop_assert_eq!(stats.bounds, BoundingBox::around(field.keys().copied()));
+                prop_assert_eq!(
+                    board.elves().sorted().collect_vec(),
+                    field.keys().copied().sorted().collect_vec()
+                );
+            }
+        }
+    }
 }
@@ -1,76 +1,34 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
-type Parsed = Vec<String>;
-
-fn snafu_digit_value(c: char) -> Result<i64, ()> {
-    match c {
-        '0' => Ok(0),
-        '1' => Ok(1),
-        '2' => Ok(2),
-        '-' => Ok(-1),
-        '=' => Ok(-2),
-        _ => Err(()),
-    }
-}
-
-fn snafu_to_decimal(s: &str) -> i64 {
-    s.chars()
-        .rev()
-        .enumerate()
-        .map(|(i, c)| snafu_digit_value(c).unwrap() * (5 as i64).pow(i as u32))
-        .sum()
-}
+pub use crate::balanced_base::{NumeralError as SnafuError, Snafu};
 
-fn decimal_to_snafu_digit(d: i64) -> Result<char, ()> {
-    match d {
-        -2 => Ok('='),
-        -1 => Ok('-'),
-        0 => Ok('0'),
-        1 => Ok('1'),
-        2 => Ok('2'),
-        _ => Err(()),
-    }
-}
-
-fn decimal_to_snafu(d: i64) -> String {
-    let mut digits = vec![];
-
-    let mut remaining = d;
-    let mut carry_over = 0;
-    while remaining > 0 {
-        let mut next_digit = carry_over + remaining % 5;
-        if next_digit > 2 {
-            next_digit -= 5;
-            carry_over = 1;
-        } else {
-            carry_over = 0;
-        }
-        digits.push(decimal_to_snafu_digit(next_digit).unwrap());
-        remaining /= 5;
-    }
-    if carry_over > 0 || digits.len() == 0 {
-        digits.push(decimal_to_snafu_digit(carry_over).unwrap());
-    }
-
-    digits.iter().rev().collect()
-}
+type Parsed = Vec<Snafu>;
 
 #[aoc_generator(day25)]
-fn parse_input(input: &str) -> Parsed {
-    input.lines().map(|line| line.to_string()).collect()
+fn parse_input(input: &str) -> Result<Parsed, SnafuError> {
+    input.lines().map(|line| line.parse()).collect()
 }
 
 #[aoc(day25, part1)]
 fn part1(input: &Parsed) -> String {
-    decimal_to_snafu(input.iter().map(|line| snafu_to_decimal(line)).sum())
+    input.iter().cloned().sum::<Snafu>().to_string()
 }
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
     use rstest::rstest;
 
     use super::*;
 
+    fn snafu_to_decimal(s: &str) -> i64 {
+        i64::try_from(&s.parse::<Snafu>().unwrap()).unwrap()
+    }
+
+    fn decimal_to_snafu(d: i64) -> String {
+        Snafu::from(d).to_string()
+    }
+
     fn input<'a>() -> &'a str {
         "1=-0-2
 12111
@@ -109,8 +67,66 @@ mod tests {
         assert_eq!(decimal_to_snafu(output), input);
     }
 
+    #[rstest]
+    #[case("1=", "1-", "12")]
+    #[case("2", "2", "1-")]
+    #[case("-", "=", "-2")]
+    #[case("1=11-2", "-2--1=", "0")]
+    #[case("2=-01", "1-0---0", "1-12=-1")]
+    fn test_snafu_add(#[case] left: Snafu, #[case] right: Snafu, #[case] sum: Snafu) {
+        assert_eq!(left.clone() + right.clone(), sum);
+        assert_eq!(sum - right, left);
+    }
+
+    #[rstest]
+    #[case(0, 17)]
+    #[case(-3, 8)]
+    #[case(2022, -12345)]
+    #[case(314159265, 2718281)]
+    fn test_snafu_mul(#[case] left: i64, #[case] right: i64) {
+        assert_eq!(
+            Snafu::from(left) * Snafu::from(right),
+            Snafu::from(left * right)
+        );
+    }
+
+    #[test]
+    fn test_snafu_ord() {
+        let numbers = ["2=", "-", "0", "1=11-2", "=", "1"]
+            .iter()
+            .map(|s| s.parse::<Snafu>().unwrap())
+            .sorted()
+            .map(|n| i64::try_from(&n).unwrap())
+            .collect_vec();
+        assert_eq!(numbers, vec![-2, -1, 0, 1, 8, 2022]);
+    }
+
+    #[test]
+    fn test_snafu_arbitrary_precision() {
+        let big = "1".repeat(40).parse::<Snafu>().unwrap();
+        assert_eq!(i64::try_from(&big), Err(SnafuError::Overflow));
+        assert_eq!(
+            (big.clone() - big.clone() + big.clone()).to_string(),
+            "1".repeat(40)
+        );
+        assert_eq!((big.clone() * -Snafu::from(1)).to_string(), "-".repeat(40));
+    }
+
+    #[test]
+    fn test_snafu_errors() {
+        assert_eq!("".parse::<Snafu>(), Err(SnafuError::Empty));
+        assert_eq!(
+            "1=3-".parse::<Snafu>(),
+            Err(SnafuError::InvalidDigit {
+                position: 2,
+                found: '3'
+            })
+        );
+        assert_eq!("00".parse::<Snafu>(), Ok(Snafu::zero()));
+    }
+
     #[test]
     fn sample1() {
-        assert_eq!(part1(&parse_input(input())), "2=-1=0");
+        assert_eq!(part1(&parse_input(input()).unwrap()), "2=-1=0");
     }
 }
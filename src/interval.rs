@@ -0,0 +1,332 @@
+use std::{
+    fmt::{self, Debug},
+    ops::{Add, Range, RangeInclusive, Sub},
+};
+
+pub trait Integer: Copy + Ord + Debug + Add<Output = Self> + Sub<Output = Self> {
+    const ZERO: Self;
+    const ONE: Self;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(impl Integer for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+        })*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+// Half-open interval `start..end`. Inclusive intervals are stored with their
+// end moved up by one, so `inclusive` can't represent an interval ending at
+// the type's maximum.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interval<T> {
+    pub start: T,
+    pub end: T,
+}
+
+impl<T: Integer> Interval<T> {
+    pub fn new(start: T, end: T) -> Self {
+        Self { start, end }
+    }
+
+    pub fn inclusive(start: T, last: T) -> Self {
+        Self::new(start, last + T::ONE)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    pub fn len(&self) -> T {
+        if self.is_empty() {
+            T::ZERO
+        } else {
+            self.end - self.start
+        }
+    }
+
+    // Last value inside the interval.
+    pub fn last(&self) -> Option<T> {
+        (!self.is_empty()).then(|| self.end - T::ONE)
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.start <= value && value < self.end
+    }
+
+    pub fn fully_contains(&self, other: &Self) -> bool {
+        other.is_empty() || (self.start <= other.start && other.end <= self.end)
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let intersection = Self::new(self.start.max(other.start), self.end.min(other.end));
+        (!intersection.is_empty()).then_some(intersection)
+    }
+
+    pub fn union(&self, other: &Self) -> IntervalSet<T> {
+        [*self, *other].into_iter().collect()
+    }
+
+    pub fn difference(&self, other: &Self) -> IntervalSet<T> {
+        IntervalSet::from(*self).difference(&IntervalSet::from(*other))
+    }
+
+    pub fn to_range(&self) -> Range<T> {
+        self.start..self.end
+    }
+}
+
+impl<T: Debug> Debug for Interval<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}..{:?}", self.start, self.end)
+    }
+}
+
+impl<T: Integer> From<Range<T>> for Interval<T> {
+    fn from(range: Range<T>) -> Self {
+        Self::new(range.start, range.end)
+    }
+}
+
+impl<T: Integer> From<RangeInclusive<T>> for Interval<T> {
+    fn from(range: RangeInclusive<T>) -> Self {
+        Self::inclusive(*range.start(), *range.end())
+    }
+}
+
+// Sorted, disjoint intervals. Overlapping or touching intervals are merged
+// on insertion, so every set has exactly one representation.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self {
+            intervals: Vec::new(),
+        }
+    }
+}
+
+impl<T: Integer> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    // Number of values in the set.
+    pub fn len(&self) -> T {
+        self.intervals
+            .iter()
+            .fold(T::ZERO, |len, interval| len + interval.len())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval<T>> {
+        self.intervals.iter()
+    }
+
+    pub fn to_ranges(&self) -> Vec<Range<T>> {
+        self.intervals.iter().map(Interval::to_range).collect()
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        if interval.is_empty() {
+            return;
+        }
+
+        // intervals[first..last] overlap or touch the new interval
+        let first = self
+            .intervals
+            .partition_point(|existing| existing.end < interval.start);
+        let last = self
+            .intervals
+            .partition_point(|existing| existing.start <= interval.end);
+        let merged = self.intervals[first..last]
+            .iter()
+            .fold(interval, |merged, existing| {
+                Interval::new(
+                    merged.start.min(existing.start),
+                    merged.end.max(existing.end),
+                )
+            });
+        self.intervals.splice(first..last, [merged]);
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let index = self
+            .intervals
+            .partition_point(|interval| interval.end <= value);
+        self.intervals
+            .get(index)
+            .is_some_and(|interval| interval.contains(value))
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.iter().chain(other.iter()).copied().collect()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intersection = Self::new();
+        let (mut a, mut b) = (self.iter().peekable(), other.iter().peekable());
+        while let (Some(left), Some(right)) = (a.peek(), b.peek()) {
+            if let Some(overlap) = left.intersection(right) {
+                intersection.intervals.push(overlap);
+            }
+            if left.end < right.end {
+                a.next();
+            } else {
+                b.next();
+            }
+        }
+        intersection
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut difference = Self::new();
+        let mut removed = other.iter().peekable();
+        for interval in self.iter() {
+            let mut start = interval.start;
+            while let Some(cut) = removed.peek() {
+                if cut.end <= start {
+                    removed.next();
+                    continue;
+                }
+                if cut.start >= interval.end {
+                    break;
+                }
+                if cut.start > start {
+                    difference.intervals.push(Interval::new(start, cut.start));
+                }
+                start = cut.end;
+                if cut.end > interval.end {
+                    break;
+                }
+                removed.next();
+            }
+            if start < interval.end {
+                difference
+                    .intervals
+                    .push(Interval::new(start, interval.end));
+            }
+        }
+        difference
+    }
+}
+
+impl<T: Debug> Debug for IntervalSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(&self.intervals).finish()
+    }
+}
+
+impl<T: Integer> From<Interval<T>> for IntervalSet<T> {
+    fn from(interval: Interval<T>) -> Self {
+        std::iter::once(interval).collect()
+    }
+}
+
+impl<T: Integer> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(intervals: I) -> Self {
+        let mut set = Self::new();
+        for interval in intervals {
+            set.insert(interval);
+        }
+        set
+    }
+}
+
+impl<T: Integer> Extend<Interval<T>> for IntervalSet<T> {
+    fn extend<I: IntoIterator<Item = Interval<T>>>(&mut self, intervals: I) {
+        for interval in intervals {
+            self.insert(interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn set(ranges: &[Range<i32>]) -> IntervalSet<i32> {
+        ranges.iter().cloned().map(Interval::from).collect()
+    }
+
+    fn values(set: &IntervalSet<i32>) -> BTreeSet<i32> {
+        set.iter()
+            .flat_map(|interval| interval.to_range())
+            .collect()
+    }
+
+    #[test]
+    fn intervals() {
+        let a = Interval::inclusive(2u32, 6);
+        let b = Interval::from(4u32..=8);
+        assert_eq!(a.len(), 5);
+        assert_eq!(a.last(), Some(6));
+        assert!(a.contains(6) && !a.contains(7));
+        assert!(a.overlaps(&b));
+        assert!(!a.overlaps(&Interval::from(7..10)));
+        assert!(a.fully_contains(&Interval::from(3..=5)));
+        assert!(!a.fully_contains(&b));
+        assert_eq!(a.intersection(&b), Some(Interval::from(4..7)));
+        assert_eq!(a.union(&b).to_ranges(), vec![2..9]);
+        assert_eq!(a.difference(&b).to_ranges(), vec![2..4]);
+        assert_eq!(
+            b.difference(&Interval::from(5..6)).to_ranges(),
+            vec![4..5, 6..9]
+        );
+        assert!(Interval::new(3, 3).is_empty());
+        assert_eq!(Interval::new(5, 3).len(), 0);
+    }
+
+    #[test]
+    fn merging() {
+        let set = set(&[5..8, 0..2, 2..3, 10..12, 7..9, 20..20]);
+        assert_eq!(set.to_ranges(), vec![0..3, 5..9, 10..12]);
+        assert_eq!(set.len(), 9);
+        assert!(set.contains(8) && !set.contains(9) && !set.contains(-1));
+        assert_eq!(format!("{set:?}"), "{0..3, 5..9, 10..12}");
+    }
+
+    proptest! {
+        #[test]
+        fn set_operations_match_value_sets(
+            a in prop::collection::vec((-20i32..20, 0i32..8), 0..6),
+            b in prop::collection::vec((-20i32..20, 0i32..8), 0..6),
+        ) {
+            let a = set(&a.iter().map(|(start, len)| *start..start + len).collect::<Vec<_>>());
+            let b = set(&b.iter().map(|(start, len)| *start..start + len).collect::<Vec<_>>());
+            let (va, vb) = (values(&a), values(&b));
+
+            prop_assert_eq!(values(&a.union(&b)), &va | &vb);
+            prop_assert_eq!(values(&a.intersection(&b)), &va & &vb);
+            prop_assert_eq!(values(&a.difference(&b)), &va - &vb);
+            prop_assert_eq!(a.len() as usize, va.len());
+            for value in -25..30 {
+                prop_assert_eq!(a.contains(value), va.contains(&value));
+            }
+
+            // canonical form: sorted, non-empty and neither overlapping nor touching
+            for set in [a.union(&b), a.intersection(&b), a.difference(&b)] {
+                prop_assert!(set.iter().all(|interval| !interval.is_empty()));
+                prop_assert!(set.intervals.windows(2).all(|pair| pair[0].end < pair[1].start));
+            }
+        }
+    }
+}
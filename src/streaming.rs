@@ -0,0 +1,103 @@
+use std::{
+    fmt::{self, Display},
+    io::{self, BufRead},
+    str::FromStr,
+};
+
+// Failure while reading records from a stream. Line numbers start at 1.
+#[derive(Debug)]
+pub enum StreamError<E> {
+    Io(io::Error),
+    Parse { line: usize, error: E },
+}
+
+impl<E: Display> Display for StreamError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Io(error) => write!(f, "read failed: {error}"),
+            StreamError::Parse { line, error } => write!(f, "line {line}: {error}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + Display> std::error::Error for StreamError<E> {}
+
+impl<E> From<io::Error> for StreamError<E> {
+    fn from(error: io::Error) -> Self {
+        StreamError::Io(error)
+    }
+}
+
+// Numbered lines of the stream, read one at a time.
+pub fn lines<R: BufRead>(reader: R) -> impl Iterator<Item = io::Result<(usize, String)>> {
+    reader
+        .lines()
+        .enumerate()
+        .map(|(i, line)| line.map(|line| (i + 1, line)))
+}
+
+// Parses every line of the stream as one record, holding only the current
+// line in memory.
+pub fn records<R: BufRead, T: FromStr>(
+    reader: R,
+) -> impl Iterator<Item = Result<T, StreamError<T::Err>>> {
+    lines(reader).map(|line| {
+        let (line, text) = line?;
+        text.parse()
+            .map_err(|error| StreamError::Parse { line, error })
+    })
+}
+
+// Groups of records separated by blank lines.
+pub fn groups<R: BufRead, T: FromStr>(
+    reader: R,
+) -> impl Iterator<Item = Result<Vec<T>, StreamError<T::Err>>> {
+    let mut lines = lines(reader);
+    std::iter::from_fn(move || {
+        let mut group = Vec::new();
+        for line in lines.by_ref() {
+            let (line, text) = match line {
+                Ok(line) => line,
+                Err(error) => return Some(Err(error.into())),
+            };
+            if text.is_empty() {
+                if group.is_empty() {
+                    continue;
+                }
+                return Some(Ok(group));
+            }
+            match text.parse() {
+                Ok(record) => group.push(record),
+                Err(error) => return Some(Err(StreamError::Parse { line, error })),
+            }
+        }
+        (!group.is_empty()).then_some(Ok(group))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::ParseIntError;
+
+    use super::*;
+
+    #[test]
+    fn parses_records() {
+        let parsed: Result<Vec<i32>, _> = records("1\n-2\n3\n".as_bytes()).collect();
+        assert_eq!(parsed.unwrap(), vec![1, -2, 3]);
+
+        let error = records::<_, i32>("1\nx\n3".as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert!(matches!(error, StreamError::Parse { line: 2, .. }));
+        assert_eq!(error.to_string(), "line 2: invalid digit found in string");
+    }
+
+    #[test]
+    fn parses_groups() {
+        let parsed: Result<Vec<Vec<u32>>, StreamError<ParseIntError>> =
+            groups("1\n2\n\n3\n\n\n4\n5\n".as_bytes()).collect();
+        assert_eq!(parsed.unwrap(), vec![vec![1, 2], vec![3], vec![4, 5]]);
+        assert_eq!(groups::<_, u32>("".as_bytes()).count(), 0);
+    }
+}